@@ -0,0 +1,72 @@
+//! A minimal VapourSynth plugin with a filter that passes the frames through unchanged.
+//!
+//! Build with `cargo build --example passthrough` and load the resulting library with
+//! `core.std.LoadPlugin()`, then use it as `core.passthrough.Passthrough(clip)`.
+//!
+//! In API 3 there's no separate activation reason: VapourSynth calls `get_frame_initial()` for the
+//! initial activation, where the filter requests the source frames, and `get_frame()` once all
+//! requested frames are ready.
+#[macro_use]
+extern crate vapoursynth;
+
+use anyhow::{anyhow, Error};
+
+use vapoursynth::core::CoreRef;
+use vapoursynth::plugins::*;
+use vapoursynth::prelude::*;
+use vapoursynth::video_info::VideoInfo;
+
+struct Passthrough<'core> {
+    source: Node<'core>,
+}
+
+impl<'core> Filter<'core> for Passthrough<'core> {
+    fn video_info(&self, _api: API, _core: CoreRef<'core>) -> Vec<VideoInfo<'core>> {
+        vec![self.source.info()]
+    }
+
+    fn get_frame_initial(
+        &self,
+        _api: API,
+        _core: CoreRef<'core>,
+        context: FrameContext,
+        n: usize,
+    ) -> Result<Option<FrameRef<'core>>, Error> {
+        self.source.request_frame_filter(context, n);
+        Ok(None)
+    }
+
+    fn get_frame(
+        &self,
+        _api: API,
+        _core: CoreRef<'core>,
+        context: FrameContext,
+        n: usize,
+    ) -> Result<FrameRef<'core>, Error> {
+        self.source
+            .get_frame_filter(context, n)
+            .ok_or_else(|| anyhow!("Couldn't get the source frame"))
+    }
+}
+
+make_filter_function! {
+    PassthroughFunction, "Passthrough"
+
+    fn create_passthrough<'core>(
+        _api: API,
+        _core: CoreRef<'core>,
+        clip: Node<'core>,
+    ) -> Result<Option<Box<dyn Filter<'core> + 'core>>, Error> {
+        Ok(Some(Box::new(Passthrough { source: clip })))
+    }
+}
+
+export_vapoursynth_plugin! {
+    Metadata {
+        identifier: "com.example.vapoursynth-rs.passthrough",
+        namespace: "passthrough",
+        name: "Passthrough Example Plugin",
+        read_only: true,
+    },
+    [PassthroughFunction::new()]
+}
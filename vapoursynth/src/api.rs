@@ -3,11 +3,11 @@
 use std::ffi::{CStr, CString, NulError};
 use std::os::raw::{c_char, c_int, c_void};
 use std::ptr::{self, NonNull};
-use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::atomic::{AtomicI32, AtomicPtr, Ordering};
 use std::{mem, panic, process};
 use vapoursynth_sys as ffi;
 
-use crate::core::CoreRef;
+use crate::core::{CoreCreationFlags, CoreRef};
 
 /// A wrapper for the VapourSynth API.
 #[derive(Debug, Clone, Copy)]
@@ -22,6 +22,9 @@ unsafe impl Sync for API {}
 /// A cached API pointer. Note that this is `*const ffi::VSAPI`, not `*mut`.
 static RAW_API: AtomicPtr<ffi::VSAPI> = AtomicPtr::new(ptr::null_mut());
 
+/// The cached runtime API version, or zero if it hasn't been determined yet.
+static RAW_API_VERSION: AtomicI32 = AtomicI32::new(0);
+
 /// VapourSynth log message types.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum MessageType {
@@ -33,6 +36,48 @@ pub enum MessageType {
     Fatal,
 }
 
+/// VapourSynth API functions that were introduced after API 3.0 or without bumping the API version.
+///
+/// Used with [`API::supports`](struct.API.html#method.supports).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ApiFunction {
+    SetMaxCacheSize,
+    SetThreadCount,
+    GetPluginPath,
+    PropGetIntArray,
+    PropGetFloatArray,
+    PropSetIntArray,
+    PropSetFloatArray,
+    LogMessage,
+    AddMessageHandler,
+    RemoveMessageHandler,
+    GetCoreInfo2,
+}
+
+impl ApiFunction {
+    /// Returns the first API version guaranteed to provide the function, as
+    /// `(major << 16) | minor`.
+    #[inline]
+    pub fn min_api_version(self) -> i32 {
+        let (major, minor) = match self {
+            ApiFunction::SetMaxCacheSize | ApiFunction::SetThreadCount => (3, 0),
+            // This was introduced in R25 without bumping the API version, so it's only guaranteed
+            // to be there starting from API 3.1.
+            ApiFunction::GetPluginPath => (3, 1),
+            ApiFunction::PropGetIntArray
+            | ApiFunction::PropGetFloatArray
+            | ApiFunction::PropSetIntArray
+            | ApiFunction::PropSetFloatArray => (3, 1),
+            ApiFunction::LogMessage => (3, 4),
+            ApiFunction::AddMessageHandler
+            | ApiFunction::RemoveMessageHandler
+            | ApiFunction::GetCoreInfo2 => (3, 6),
+        };
+
+        (major << 16) | minor
+    }
+}
+
 // Macros for implementing repetitive functions.
 macro_rules! prop_get_something {
     ($name:ident, $func:ident, $rv:ty) => {
@@ -76,6 +121,31 @@ macro_rules! prop_set_something {
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct MessageHandlerId(ffi::VSMessageHandlerId);
 
+// Returns the highest API version the runtime provides, starting from the one the crate was built
+// against. Without a way of requesting the API, the built against version is the best known bound.
+fn probe_api_version() -> i32 {
+    #[allow(unused_mut)]
+    let mut version = ffi::VAPOURSYNTH_API_VERSION;
+
+    #[cfg(any(
+        feature = "vapoursynth-functions",
+        all(feature = "vsscript-functions", feature = "gte-vsscript-api-32")
+    ))]
+    while version & 0xffff < 0xffff {
+        #[cfg(all(feature = "vsscript-functions", feature = "gte-vsscript-api-32"))]
+        let handle = unsafe { ffi::vsscript_getVSApi2(version + 1) };
+        #[cfg(not(all(feature = "vsscript-functions", feature = "gte-vsscript-api-32")))]
+        let handle = unsafe { ffi::getVapourSynthAPI(version + 1) };
+
+        if handle.is_null() {
+            break;
+        }
+        version += 1;
+    }
+
+    version
+}
+
 impl API {
     /// Retrieves the VapourSynth API.
     ///
@@ -168,6 +238,40 @@ impl API {
         RAW_API.store(handle as *mut _, Ordering::Relaxed);
     }
 
+    /// Returns the API version provided by the running VapourSynth, as `(major << 16) | minor`.
+    ///
+    /// This is at least the version the crate was built against (selected with features, see the
+    /// crate-level docs), since VapourSynth refuses to provide an older API. The newer versions are
+    /// probed on the first call, however the API was retrieved, and the result is cached. Probing
+    /// needs `getVapourSynthAPI()` or `vsscript_getVSApi2()`, so without the
+    /// `vapoursynth-functions` feature or the `vsscript-functions` and `gte-vsscript-api-32`
+    /// features this is always the version the crate was built against.
+    #[inline]
+    pub fn version(self) -> i32 {
+        let version = RAW_API_VERSION.load(Ordering::Relaxed);
+        if version != 0 {
+            return version;
+        }
+
+        let version = probe_api_version();
+        RAW_API_VERSION.store(version, Ordering::Relaxed);
+        version
+    }
+
+    /// Returns whether the API provides the given function.
+    ///
+    /// The functions the crate was built against (selected with features) are always provided, so
+    /// this only matters for functions newer than that.
+    ///
+    /// This compares the runtime API version with the version the function was introduced in,
+    /// which guards against running on an older core than the one the headers describe. The
+    /// function pointers can't be checked directly: an older core provides a shorter `VSAPI`
+    /// struct, without the newer fields.
+    #[inline]
+    pub fn supports(self, function: ApiFunction) -> bool {
+        self.version() >= function.min_api_version()
+    }
+
     /// Sends a message through VapourSynth’s logging framework.
     #[cfg(feature = "gte-vapoursynth-api-34")]
     #[inline]
@@ -706,7 +810,7 @@ impl API {
     /// Retrieves an array of integers from a map.
     ///
     /// # Safety
-    /// The caller must ensure `map` and `key` are valid.
+    /// The caller must ensure `map` and `key` are valid, and that the API supports the function.
     #[cfg(feature = "gte-vapoursynth-api-31")]
     #[inline]
     pub(crate) unsafe fn prop_get_int_array(
@@ -721,7 +825,7 @@ impl API {
     /// Retrieves an array of floating point numbers from a map.
     ///
     /// # Safety
-    /// The caller must ensure `map` and `key` are valid.
+    /// The caller must ensure `map` and `key` are valid, and that the API supports the function.
     #[cfg(feature = "gte-vapoursynth-api-31")]
     #[inline]
     pub(crate) unsafe fn prop_get_float_array(
@@ -758,7 +862,7 @@ impl API {
     /// Adds an array of integers to the map.
     ///
     /// # Safety
-    /// The caller must ensure `map` and `key` are valid.
+    /// The caller must ensure `map` and `key` are valid, and that the API supports the function.
     ///
     /// # Panics
     /// Panics if `value.len()` can't fit in an `i32`.
@@ -780,7 +884,7 @@ impl API {
     /// Adds an array of floating point numbers to the map.
     ///
     /// # Safety
-    /// The caller must ensure `map` and `key` are valid.
+    /// The caller must ensure `map` and `key` are valid, and that the API supports the function.
     ///
     /// # Panics
     /// Panics if `value.len()` can't fit in an `i32`.
@@ -1062,7 +1166,7 @@ impl API {
     /// VapourSynth retains ownership of the returned pointer.
     ///
     /// # Safety
-    /// The caller must ensure all pointers are valid.
+    /// The caller must ensure all pointers are valid, and that the API supports the function.
     // This was introduced in R25 without bumping the API version (R3) but we must be sure it's
     // there, so require R3.1.
     #[cfg(feature = "gte-vapoursynth-api-31")]
@@ -1177,6 +1281,34 @@ impl API {
             CoreRef::from_ptr(handle)
         }
     }
+
+    /// Creates and returns a new core with the given creation flags.
+    ///
+    /// Core creation flags require VapourSynth API 4. The API 3 versions supported by this crate
+    /// can't honor them, so this falls back to `create_core()`, which creates a core with the
+    /// default behavior (for example, with plugin autoloading). When non-empty flags are ignored,
+    /// a warning is sent through `log()` (VapourSynth API 3.4 or greater).
+    ///
+    /// See `create_core()` for the notes on the lifetime of the core.
+    #[inline]
+    pub fn create_core_with_flags<'core>(
+        self,
+        threads: i32,
+        flags: CoreCreationFlags,
+    ) -> CoreRef<'core> {
+        if !flags.is_empty() {
+            #[cfg(feature = "gte-vapoursynth-api-34")]
+            {
+                let message = format!(
+                    "Core creation flags require VapourSynth API 4, ignoring {:?}",
+                    flags
+                );
+                let _ = self.log(MessageType::Warning, &message);
+            }
+        }
+
+        self.create_core(threads)
+    }
 }
 
 impl MessageType {
@@ -0,0 +1,100 @@
+//! Clips for chaining filter invocations.
+
+use std::ffi::NulError;
+
+use thiserror::Error;
+
+use crate::core::CoreRef;
+use crate::map::{self, OwnedMap};
+use crate::node::Node;
+
+/// The error type for `Clip` operations.
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Couldn't convert to a CString")]
+    CStringConversion(#[from] NulError),
+    #[error("There's no plugin with the namespace {}", _0)]
+    NoSuchPlugin(String),
+    #[error("Couldn't invoke the filter: {}", _0)]
+    Invoke(String),
+    #[error(transparent)]
+    Map(#[from] map::Error),
+}
+
+/// A node together with the core it belongs to, allowing to chain filter invocations.
+///
+/// ```no_run
+/// # use vapoursynth::prelude::*;
+/// # use vapoursynth::clip::Clip;
+/// # fn f(api: API, clip: Clip) -> Result<(), vapoursynth::clip::Error> {
+/// let mut args = OwnedMap::new(api);
+/// args.set_int("first", 10)?;
+/// let flip_args = OwnedMap::new(api);
+///
+/// let clip = clip
+///     .invoke("std", "Trim", args)?
+///     .invoke("std", "FlipVertical", flip_args)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Clip<'core> {
+    core: CoreRef<'core>,
+    node: Node<'core>,
+}
+
+impl<'core> Clip<'core> {
+    /// Creates a clip from a node belonging to `core`.
+    #[inline]
+    pub fn new(core: CoreRef<'core>, node: Node<'core>) -> Self {
+        Self { core, node }
+    }
+
+    /// Returns the core of the clip.
+    #[inline]
+    pub fn core(&self) -> CoreRef<'core> {
+        self.core
+    }
+
+    /// Returns the node of the clip.
+    #[inline]
+    pub fn node(&self) -> &Node<'core> {
+        &self.node
+    }
+
+    /// Returns the node of the clip, consuming the clip.
+    #[inline]
+    pub fn into_node(self) -> Node<'core> {
+        self.node
+    }
+
+    /// Returns the length of the clip, or `None` if it's unknown.
+    #[inline]
+    pub fn num_frames(&self) -> Option<usize> {
+        self.node.info().frame_count()
+    }
+
+    /// Invokes the filter `function` from the plugin with the given `namespace` on this clip.
+    ///
+    /// The clip is passed to the filter as the `clip` argument, in addition to the `args`.
+    /// Returns the first clip returned by the filter.
+    pub fn invoke(
+        &self,
+        namespace: &str,
+        function: &str,
+        mut args: OwnedMap<'core>,
+    ) -> Result<Clip<'core>, Error> {
+        let plugin = self
+            .core
+            .get_plugin_by_namespace(namespace)?
+            .ok_or_else(|| Error::NoSuchPlugin(namespace.to_owned()))?;
+
+        args.set_node("clip", &self.node)?;
+        let rv = plugin.invoke(function, &args)?;
+        if let Some(error) = rv.error() {
+            return Err(Error::Invoke(error.into_owned()));
+        }
+
+        Ok(Clip::new(self.core, rv.get_node("clip")?))
+    }
+}
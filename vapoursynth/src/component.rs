@@ -50,3 +50,10 @@ unsafe impl Component for f32 {
         format.sample_type() == SampleType::Float && format.bytes_per_sample() == 4
     }
 }
+
+unsafe impl Component for f64 {
+    #[inline]
+    fn is_valid(format: Format) -> bool {
+        format.sample_type() == SampleType::Float && format.bytes_per_sample() == 8
+    }
+}
@@ -3,13 +3,34 @@
 use std::ffi::{CStr, CString, NulError};
 use std::fmt;
 use std::marker::PhantomData;
-use std::ptr::NonNull;
+use std::ptr::{self, NonNull};
 use vapoursynth_sys as ffi;
 
 use crate::api::API;
 use crate::format::{ColorFamily, Format, FormatID, SampleType};
+use crate::frame::{self, FrameRefMut};
 use crate::map::OwnedMap;
 use crate::plugin::Plugin;
+use crate::video_info::Resolution;
+
+bitflags! {
+    /// Core creation flags.
+    ///
+    /// These flags are only supported by VapourSynth API 4, where they are passed to
+    /// `createCore()`. The API 3 versions supported by this crate can only create cores with the
+    /// default behavior, which corresponds to empty flags, so `API::create_core_with_flags()`
+    /// ignores the other flags.
+    #[derive(Default)]
+    pub struct CoreCreationFlags: i32 {
+        /// Enables the graph inspection functions of the API (`ccfEnableGraphInspection`).
+        const ENABLE_GRAPH_INSPECTION = 1;
+        /// Disables the autoloading of the user and system plugins (`ccfDisableAutoLoading`).
+        const DISABLE_AUTO_LOADING = 2;
+        /// Keeps the plugin libraries loaded when the core is freed
+        /// (`ccfDisableLibraryUnloading`).
+        const DISABLE_LIBRARY_UNLOADING = 4;
+    }
+}
 
 /// Contains information about a VapourSynth core.
 #[derive(Debug, Clone, Copy, Hash)]
@@ -90,6 +111,15 @@ impl<'core> CoreRef<'core> {
         }
     }
 
+    /// Returns the current number of worker threads.
+    ///
+    /// The core info is queried on every call, so this reflects changes made with
+    /// `set_thread_count()`.
+    #[inline]
+    pub fn num_threads(self) -> usize {
+        self.info().num_threads
+    }
+
     /// Retrieves a registered or preset `Format` by its id. The id can be of a previously
     /// registered format, or one of the `PresetFormat`.
     #[inline]
@@ -171,6 +201,65 @@ impl<'core> CoreRef<'core> {
         unsafe { OwnedMap::from_ptr(API::get_cached().get_plugins(self.handle.as_ptr())) }
     }
 
+    /// Creates a new frame and fills it with the given plane data.
+    ///
+    /// Each element of `planes` contains the tightly packed pixel data of the corresponding plane,
+    /// that is, `width(plane) * height(plane) * format.bytes_per_sample()` bytes with no padding
+    /// between the rows. The rows are copied into the frame respecting its stride.
+    ///
+    /// The width and the height must be divisible by the subsampling factors of the format.
+    ///
+    /// # Panics
+    /// Panics if `width` or `height` doesn't fit into an `i32`.
+    pub fn new_video_frame_from_planes(
+        &self,
+        format: Format<'core>,
+        width: usize,
+        height: usize,
+        planes: &[&[u8]],
+    ) -> Result<FrameRefMut<'core>, frame::Error> {
+        format.validate_dimensions(width, height)?;
+
+        if planes.len() != format.plane_count() {
+            return Err(frame::Error::WrongPlaneCount {
+                expected: format.plane_count(),
+                got: planes.len(),
+            });
+        }
+
+        let resolution = Resolution { width, height };
+        let mut frame = unsafe { FrameRefMut::new_uninitialized(*self, None, format, resolution) };
+
+        let bytes_per_sample = frame.bytes_per_sample();
+        for (plane, data) in planes.iter().enumerate() {
+            let row_length = frame.width(plane) * bytes_per_sample;
+            let expected = row_length * frame.height(plane);
+            if data.len() != expected {
+                return Err(frame::Error::WrongPlaneSize {
+                    plane,
+                    expected,
+                    got: data.len(),
+                });
+            }
+        }
+
+        for (plane, data) in planes.iter().enumerate() {
+            let row_length = frame.width(plane) * bytes_per_sample;
+            let stride = frame.stride(plane);
+            let ptr = frame.data_ptr_mut(plane);
+
+            for (row, src) in data.chunks_exact(row_length).enumerate() {
+                // Safety: the frame plane holds `height()` rows of `stride()` bytes, each of which
+                // has room for `row_length` bytes.
+                unsafe {
+                    ptr::copy_nonoverlapping(src.as_ptr(), ptr.add(stride * row), row_length);
+                }
+            }
+        }
+
+        Ok(frame)
+    }
+
     /// Sets the maximum size of the framebuffer cache. Returns the new maximum size.
     #[cfg(feature = "gte-vapoursynth-api-36")]
     #[inline]
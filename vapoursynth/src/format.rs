@@ -6,12 +6,38 @@ use std::marker::PhantomData;
 use std::ops::Deref;
 use vapoursynth_sys as ffi;
 
+use thiserror::Error;
+
 /// Contains information about a video format.
 #[derive(Debug, Clone, Copy)]
 pub struct Format<'core> {
     handle: &'core ffi::VSFormat,
 }
 
+/// The error type for format operations.
+#[derive(Error, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Error {
+    #[error("The {dimension} ({value}) must be divisible by {divisor} for this format")]
+    InvalidDimension {
+        dimension: &'static str,
+        value: usize,
+        divisor: usize,
+    },
+    #[error("Unsupported subsampling: {w} horizontally, {h} vertically")]
+    UnsupportedSubsampling { w: u8, h: u8 },
+    #[error("The color families differ: {left} and {right}")]
+    ColorFamilyMismatch {
+        left: ColorFamily,
+        right: ColorFamily,
+    },
+    #[error("The sample types differ: {left} and {right}")]
+    SampleTypeMismatch { left: SampleType, right: SampleType },
+    #[error("The bits per sample differ: {left} and {right}")]
+    BitsPerSampleMismatch { left: u8, right: u8 },
+    #[error("The subsampling differs: {left:?} and {right:?}")]
+    SubsamplingMismatch { left: (u8, u8), right: (u8, u8) },
+}
+
 /// Preset VapourSynth formats.
 ///
 /// The presets suffixed with H and S have floating point sample type. The H and S suffixes stand
@@ -78,6 +104,19 @@ pub enum SampleType {
     Float,
 }
 
+/// Chroma subsampling modes.
+///
+/// The names follow the J:a:b notation.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Subsampling {
+    S444,
+    S440,
+    S422,
+    S420,
+    S411,
+    S410,
+}
+
 /// A unique format identifier.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct FormatID(pub(crate) i32);
@@ -145,6 +184,24 @@ impl<'core> Format<'core> {
         }
     }
 
+    /// Returns `true` if the color family of this format is `Gray`.
+    #[inline]
+    pub fn is_gray(self) -> bool {
+        self.color_family() == ColorFamily::Gray
+    }
+
+    /// Returns `true` if the color family of this format is `RGB`.
+    #[inline]
+    pub fn is_rgb(self) -> bool {
+        self.color_family() == ColorFamily::RGB
+    }
+
+    /// Returns `true` if the color family of this format is `YUV`.
+    #[inline]
+    pub fn is_yuv(self) -> bool {
+        self.color_family() == ColorFamily::YUV
+    }
+
     /// Gets the sample type of this format.
     #[inline]
     pub fn sample_type(self) -> SampleType {
@@ -172,6 +229,47 @@ impl<'core> Format<'core> {
         rv as u8
     }
 
+    /// Returns the number of bytes needed to store a frame of this format with the given
+    /// resolution, with all planes tightly packed (without any padding between the rows).
+    pub fn frame_size_bytes(self, width: usize, height: usize) -> usize {
+        let bytes_per_sample = usize::from(self.bytes_per_sample());
+
+        (0..self.plane_count())
+            .map(|plane| {
+                let (width, height) = if plane == 0 {
+                    (width, height)
+                } else {
+                    (
+                        width >> self.sub_sampling_w(),
+                        height >> self.sub_sampling_h(),
+                    )
+                };
+
+                width * height * bytes_per_sample
+            })
+            .sum()
+    }
+
+    /// Checks that a frame of this format can have the given resolution, that is, that the width
+    /// and the height are divisible by the horizontal and vertical subsampling factors.
+    pub fn validate_dimensions(self, width: usize, height: usize) -> Result<(), Error> {
+        let check = |dimension, value: usize, sub_sampling: u8| {
+            let divisor = 1 << sub_sampling;
+            if value & (divisor - 1) == 0 {
+                Ok(())
+            } else {
+                Err(Error::InvalidDimension {
+                    dimension,
+                    value,
+                    divisor,
+                })
+            }
+        };
+
+        check("width", width, self.sub_sampling_w())?;
+        check("height", height, self.sub_sampling_h())
+    }
+
     /// log2 subsampling factor, applied to second and third plane.
     #[inline]
     pub fn sub_sampling_w(self) -> u8 {
@@ -187,6 +285,99 @@ impl<'core> Format<'core> {
         debug_assert!(rv >= 0 && rv <= i32::from(u8::max_value()));
         rv as u8
     }
+
+    /// Returns `true` if frames of this and the `other` format can be combined in two-input
+    /// operations, like computing the difference or merging.
+    ///
+    /// See `compatibility()` for the details.
+    #[inline]
+    pub fn is_compatible_with(self, other: Format) -> bool {
+        self.compatibility(other).is_ok()
+    }
+
+    /// Checks whether frames of this and the `other` format can be combined in two-input
+    /// operations, returning the reason if they can't.
+    ///
+    /// The formats are compatible if they have the same color family, sample type, bits per
+    /// sample and subsampling.
+    pub fn compatibility(self, other: Format) -> Result<(), Error> {
+        if self.color_family() != other.color_family() {
+            return Err(Error::ColorFamilyMismatch {
+                left: self.color_family(),
+                right: other.color_family(),
+            });
+        }
+
+        if self.sample_type() != other.sample_type() {
+            return Err(Error::SampleTypeMismatch {
+                left: self.sample_type(),
+                right: other.sample_type(),
+            });
+        }
+
+        if self.bits_per_sample() != other.bits_per_sample() {
+            return Err(Error::BitsPerSampleMismatch {
+                left: self.bits_per_sample(),
+                right: other.bits_per_sample(),
+            });
+        }
+
+        let left = (self.sub_sampling_w(), self.sub_sampling_h());
+        let right = (other.sub_sampling_w(), other.sub_sampling_h());
+        if left != right {
+            return Err(Error::SubsamplingMismatch { left, right });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the chroma subsampling of the format.
+    #[inline]
+    pub fn subsampling(self) -> Result<Subsampling, Error> {
+        Subsampling::from_wh(self.sub_sampling_w(), self.sub_sampling_h())
+    }
+}
+
+impl Subsampling {
+    /// Converts log2 horizontal and vertical subsampling factors into a `Subsampling`.
+    #[inline]
+    pub fn from_wh(w: u8, h: u8) -> Result<Self, Error> {
+        match (w, h) {
+            (0, 0) => Ok(Subsampling::S444),
+            (0, 1) => Ok(Subsampling::S440),
+            (1, 0) => Ok(Subsampling::S422),
+            (1, 1) => Ok(Subsampling::S420),
+            (2, 0) => Ok(Subsampling::S411),
+            (2, 2) => Ok(Subsampling::S410),
+            _ => Err(Error::UnsupportedSubsampling { w, h }),
+        }
+    }
+
+    /// Returns the log2 horizontal and vertical subsampling factors.
+    #[inline]
+    pub fn to_wh(self) -> (u8, u8) {
+        match self {
+            Subsampling::S444 => (0, 0),
+            Subsampling::S440 => (0, 1),
+            Subsampling::S422 => (1, 0),
+            Subsampling::S420 => (1, 1),
+            Subsampling::S411 => (2, 0),
+            Subsampling::S410 => (2, 2),
+        }
+    }
+
+    /// Returns the colorspace tag used in YUV4MPEG2 headers.
+    #[inline]
+    pub fn y4m_tag(self) -> &'static str {
+        match self {
+            Subsampling::S444 => "444",
+            Subsampling::S440 => "440",
+            Subsampling::S422 => "422",
+            Subsampling::S420 => "420",
+            Subsampling::S411 => "411",
+            Subsampling::S410 => "410",
+        }
+    }
 }
 
 impl From<PresetFormat> for FormatID {
@@ -6,13 +6,16 @@ use std::ptr::{self, NonNull};
 use std::{mem, slice};
 use vapoursynth_sys as ffi;
 
+#[cfg(feature = "f16-pixel-type")]
+use half::f16;
+
 use thiserror::Error;
 
 use crate::api::API;
 use crate::component::Component;
 use crate::core::CoreRef;
-use crate::format::Format;
-use crate::map::{MapRef, MapRefMut};
+use crate::format::{self, Format, SampleType};
+use crate::map::{self, MapRef, MapRefMut, OwnedMap};
 use crate::video_info::Resolution;
 
 /// An error indicating that the frame data has non-zero padding.
@@ -20,6 +23,31 @@ use crate::video_info::Resolution;
 #[error("Frame data has non-zero padding: {}", _0)]
 pub struct NonZeroPadding(usize);
 
+/// The error type for frame operations.
+#[derive(Error, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Error {
+    #[error("Expected {expected} planes, got {got}")]
+    WrongPlaneCount { expected: usize, got: usize },
+    #[error("Plane {plane} has a wrong size: expected {expected} bytes, got {got}")]
+    WrongPlaneSize {
+        plane: usize,
+        expected: usize,
+        got: usize,
+    },
+    #[error("The frames have different formats")]
+    FormatMismatch,
+    #[error("The frames have different resolutions")]
+    ResolutionMismatch,
+    #[error("The operation doesn't support this format")]
+    UnsupportedFormat,
+    #[error("The component type is invalid for the frame format")]
+    InvalidComponentType,
+    #[error(transparent)]
+    NonZeroPadding(#[from] NonZeroPadding),
+    #[error(transparent)]
+    Format(#[from] format::Error),
+}
+
 /// One frame of a clip.
 // This type is intended to be publicly used only in reference form.
 #[derive(Debug)]
@@ -28,6 +56,8 @@ pub struct Frame<'core> {
     handle: NonNull<ffi::VSFrameRef>,
     // The cached frame format for fast access.
     format: Format<'core>,
+    // Whether this frame is owned by a `FrameRefMut`.
+    writable: bool,
     _owner: PhantomData<&'core ()>,
 }
 
@@ -45,6 +75,12 @@ pub struct FrameRefMut<'core> {
     frame: Frame<'core>,
 }
 
+// SAFETY: VapourSynth frames aren't tied to the thread that created them: frame references are
+// routinely passed between the worker threads and released on any of them, and the reference
+// counting is atomic. Reading a frame's data and properties concurrently is allowed, as frames
+// are only written through `FrameRefMut`, which is the sole owner of a new or copied frame and
+// hands out mutable access only through `&mut`. Thus `FrameRef` and `FrameRefMut` are `Send`, and
+// `&Frame` can be shared between threads.
 unsafe impl<'core> Send for Frame<'core> {}
 unsafe impl<'core> Sync for Frame<'core> {}
 
@@ -125,6 +161,17 @@ impl<'core> FrameRef<'core> {
             frame: Frame::from_ptr(handle),
         }
     }
+
+    /// Returns a copy of the frame properties, dropping the frame.
+    ///
+    /// This is useful when only the metadata is needed, as the pixel data can be freed right away.
+    /// See `Map::to_owned_map()` for how the values are copied.
+    #[inline]
+    pub fn into_props(self) -> OwnedMap<'core> {
+        let props: MapRef<'_, 'core> =
+            unsafe { MapRef::from_ptr(API::get_cached().get_frame_props_ro(&self)) };
+        props.to_owned_map()
+    }
 }
 
 impl<'core> FrameRefMut<'core> {
@@ -135,7 +182,7 @@ impl<'core> FrameRefMut<'core> {
     #[inline]
     pub(crate) unsafe fn from_ptr(handle: *mut ffi::VSFrameRef) -> Self {
         Self {
-            frame: Frame::from_ptr(handle),
+            frame: Frame::from_ptr_mut(handle),
         }
     }
 
@@ -147,7 +194,7 @@ impl<'core> FrameRefMut<'core> {
     #[inline]
     pub fn copy_of(core: CoreRef, frame: &Frame<'core>) -> Self {
         Self {
-            frame: unsafe { Frame::from_ptr(API::get_cached().copy_frame(frame, core.ptr())) },
+            frame: unsafe { Frame::from_ptr_mut(API::get_cached().copy_frame(frame, core.ptr())) },
         }
     }
 
@@ -160,7 +207,9 @@ impl<'core> FrameRefMut<'core> {
     /// the docs for `std::mem::uninitialized()` for more information.
     ///
     /// # Panics
-    /// Panics if the given resolution has components that don't fit into an `i32`.
+    /// Panics if the given resolution has components that don't fit into an `i32`, or if they
+    /// aren't divisible by the subsampling factors of `format` (see
+    /// `Format::validate_dimensions()`).
     #[inline]
     pub unsafe fn new_uninitialized(
         core: CoreRef<'core>,
@@ -170,10 +219,13 @@ impl<'core> FrameRefMut<'core> {
     ) -> Self {
         assert!(resolution.width <= i32::max_value() as usize);
         assert!(resolution.height <= i32::max_value() as usize);
+        if let Err(error) = format.validate_dimensions(resolution.width, resolution.height) {
+            panic!("{}", error);
+        }
 
         Self {
             frame: unsafe {
-                Frame::from_ptr(API::get_cached().new_video_frame(
+                Frame::from_ptr_mut(API::get_cached().new_video_frame(
                     &format,
                     resolution.width as i32,
                     resolution.height as i32,
@@ -183,12 +235,28 @@ impl<'core> FrameRefMut<'core> {
             },
         }
     }
+
+    /// Deletes the given frame property.
+    ///
+    /// Returns `map::Error::KeyNotFound` if the frame doesn't have this property.
+    #[inline]
+    pub fn delete_prop(&mut self, key: &str) -> Result<(), map::Error> {
+        self.props_mut().delete_key(key)
+    }
+
+    /// Deletes all frame properties.
+    #[inline]
+    pub fn clear_props(&mut self) {
+        self.props_mut().clear();
+    }
 }
 
 impl<'core> From<FrameRefMut<'core>> for FrameRef<'core> {
     #[inline]
     fn from(x: FrameRefMut<'core>) -> Self {
-        Self { frame: x.frame }
+        let mut frame = x.frame;
+        frame.writable = false;
+        Self { frame }
     }
 }
 
@@ -207,16 +275,47 @@ impl<'core> Frame<'core> {
                 let ptr = API::get_cached().get_frame_format(&*handle);
                 Format::from_ptr(ptr)
             },
+            writable: false,
             _owner: PhantomData,
         }
     }
 
+    /// Converts a pointer to a mutable frame to a writable frame.
+    ///
+    /// # Safety
+    /// The caller needs to ensure the pointer and the lifetime is valid, and that the resulting
+    /// `Frame` gets put into `FrameRefMut`.
+    #[inline]
+    unsafe fn from_ptr_mut(handle: *mut ffi::VSFrameRef) -> Self {
+        let mut frame = Self::from_ptr(handle);
+        frame.writable = true;
+        frame
+    }
+
+    /// Returns `true` if the frame data can be modified.
+    ///
+    /// Only frames owned by a `FrameRefMut` (newly created or copied frames) are writable. Frames
+    /// obtained from nodes and maps are shared and must be copied with `FrameRefMut::copy_of()`
+    /// before mutation.
+    #[inline]
+    pub fn is_writable(&self) -> bool {
+        self.writable
+    }
+
     /// Returns the frame format.
     #[inline]
     pub fn format(&self) -> Format<'core> {
         self.format
     }
 
+    /// Returns the number of bytes needed for a sample.
+    ///
+    /// This is a shortcut for `format().bytes_per_sample()`, which is the same for every plane.
+    #[inline]
+    pub fn bytes_per_sample(&self) -> usize {
+        usize::from(self.format.bytes_per_sample())
+    }
+
     /// Returns the width of a plane, in pixels.
     ///
     /// The width depends on the plane number because of the possible chroma subsampling.
@@ -259,6 +358,24 @@ impl<'core> Frame<'core> {
         }
     }
 
+    /// Returns the number of pixels in a plane, that is, `width(plane) * height(plane)`.
+    ///
+    /// # Panics
+    /// Panics if `plane >= format().plane_count()`.
+    #[inline]
+    pub fn pixel_count(&self, plane: usize) -> usize {
+        self.width(plane) * self.height(plane)
+    }
+
+    /// Returns the number of pixels in the first plane, which is the luma plane for YUV formats.
+    ///
+    /// Unlike the other planes, the first plane is never subsampled, so this is the pixel count of
+    /// the whole frame.
+    #[inline]
+    pub fn luma_pixel_count(&self) -> usize {
+        self.pixel_count(0)
+    }
+
     /// Returns the distance in bytes between two consecutive lines of a plane.
     ///
     /// # Panics
@@ -270,6 +387,18 @@ impl<'core> Frame<'core> {
         unsafe { API::get_cached().get_frame_stride(self, plane as i32) as usize }
     }
 
+    /// Returns the number of padding bytes at the end of each line of a plane.
+    ///
+    /// This is `stride()` minus the size of the pixel data in a line. When it's zero, the plane
+    /// data is contiguous and `plane_contiguous()` returns `Some`.
+    ///
+    /// # Panics
+    /// Panics if `plane >= format().plane_count()`.
+    #[inline]
+    pub fn plane_padding(&self, plane: usize) -> usize {
+        self.stride(plane) - self.width(plane) * self.bytes_per_sample()
+    }
+
     /// Returns a slice of a plane's pixel row.
     ///
     /// # Panics
@@ -329,7 +458,7 @@ impl<'core> Frame<'core> {
         assert!(T::is_valid(self.format()));
 
         let stride = self.stride(plane);
-        let width_in_bytes = self.width(plane) * usize::from(self.format().bytes_per_sample());
+        let width_in_bytes = self.width(plane) * self.bytes_per_sample();
         if stride != width_in_bytes {
             return Err(NonZeroPadding(stride - width_in_bytes));
         }
@@ -354,7 +483,7 @@ impl<'core> Frame<'core> {
         assert!(T::is_valid(self.format()));
 
         let stride = self.stride(plane);
-        let width_in_bytes = self.width(plane) * usize::from(self.format().bytes_per_sample());
+        let width_in_bytes = self.width(plane) * self.bytes_per_sample();
         if stride != width_in_bytes {
             return Err(NonZeroPadding(stride - width_in_bytes));
         }
@@ -366,13 +495,53 @@ impl<'core> Frame<'core> {
         Ok(unsafe { slice::from_raw_parts_mut(ptr as *mut T, length) })
     }
 
+    /// Returns a slice of the plane's pixels for double precision float formats.
+    ///
+    /// This is a non-panicking version of `plane::<f64>()`: an error is returned if the format
+    /// doesn't have 64-bit float samples or if the pixel data has non-zero padding.
+    ///
+    /// # Panics
+    /// Panics if `plane >= format().plane_count()`.
+    #[inline]
+    pub fn plane_f64(&self, plane: usize) -> Result<&[f64], Error> {
+        if !f64::is_valid(self.format()) {
+            return Err(Error::InvalidComponentType);
+        }
+
+        Ok(self.plane(plane)?)
+    }
+
+    /// Returns a copy of the plane's pixel data with the row padding removed.
+    ///
+    /// The length of the returned vector is `height() * width() * format().bytes_per_sample()`.
+    ///
+    /// # Panics
+    /// Panics if `plane >= format().plane_count()`.
+    pub fn plane_packed(&self, plane: usize) -> Vec<u8> {
+        if let Some(data) = self.plane_contiguous(plane) {
+            return data.to_vec();
+        }
+
+        let mut rv =
+            Vec::with_capacity(self.height(plane) * self.width(plane) * self.bytes_per_sample());
+        for row in self.rows(plane) {
+            rv.extend_from_slice(row);
+        }
+        rv
+    }
+
     /// Returns a pointer to the plane's pixels.
     ///
     /// The pointer points to an array with a length of `height() * stride()` and is valid for as
     /// long as the frame is alive.
     ///
+    /// This is meant for passing the plane to SIMD or C routines which take a pointer and a
+    /// stride: no slice is constructed, and the stride has to be obtained separately with
+    /// `stride()`. Obtaining the pointer is safe, dereferencing it is not.
+    ///
     /// # Panics
     /// Panics if `plane >= format().plane_count()`.
+    #[doc(alias = "plane_ptr")]
     #[inline]
     pub fn data_ptr(&self, plane: usize) -> *const u8 {
         assert!(plane < self.format().plane_count());
@@ -385,8 +554,11 @@ impl<'core> Frame<'core> {
     /// The pointer points to an array with a length of `height() * stride()` and is valid for as
     /// long as the frame is alive.
     ///
+    /// Like `data_ptr()`, this is meant for FFI and SIMD code working with a pointer and a stride.
+    ///
     /// # Panics
     /// Panics if `plane >= format().plane_count()`.
+    #[doc(alias = "plane_ptr_mut")]
     #[inline]
     pub fn data_ptr_mut(&mut self, plane: usize) -> *mut u8 {
         assert!(plane < self.format().plane_count());
@@ -412,11 +584,45 @@ impl<'core> Frame<'core> {
         let offset = offset as isize;
 
         let row_ptr = unsafe { ptr.offset(offset) };
-        let width = self.width(plane) * usize::from(self.format().bytes_per_sample());
+        let width = self.width(plane) * self.bytes_per_sample();
 
         unsafe { slice::from_raw_parts(row_ptr, width) }
     }
 
+    /// Returns an iterator over the plane's pixel rows.
+    ///
+    /// There are `height()` rows, each of them of the same length as returned by `data_row()`,
+    /// so the padding is excluded.
+    ///
+    /// # Panics
+    /// Panics if `plane >= format().plane_count()`.
+    #[inline]
+    pub fn rows(&self, plane: usize) -> impl Iterator<Item = &[u8]> + '_ {
+        assert!(plane < self.format().plane_count());
+
+        (0..self.height(plane)).map(move |row| self.data_row(plane, row))
+    }
+
+    /// Returns an iterator over the plane's mutable pixel rows.
+    ///
+    /// There are `height()` rows, each of them of the same length as returned by
+    /// `data_row_mut()`, so the padding is excluded.
+    ///
+    /// # Panics
+    /// Panics if `plane >= format().plane_count()`.
+    #[inline]
+    pub fn rows_mut(&mut self, plane: usize) -> impl Iterator<Item = &mut [u8]> + '_ {
+        assert!(plane < self.format().plane_count());
+
+        let stride = self.stride(plane);
+        let width = self.width(plane) * self.bytes_per_sample();
+        let ptr = self.data_ptr_mut(plane);
+
+        // The rows don't overlap since `width <= stride`.
+        (0..self.height(plane))
+            .map(move |row| unsafe { slice::from_raw_parts_mut(ptr.add(stride * row), width) })
+    }
+
     /// Returns a mutable slice of a plane's pixel row.
     ///
     /// The length of the returned slice is equal to `width() * format().bytes_per_sample()`.
@@ -435,7 +641,7 @@ impl<'core> Frame<'core> {
         let offset = offset as isize;
 
         let row_ptr = unsafe { ptr.offset(offset) };
-        let width = self.width(plane) * usize::from(self.format().bytes_per_sample());
+        let width = self.width(plane) * self.bytes_per_sample();
 
         unsafe { slice::from_raw_parts_mut(row_ptr, width) }
     }
@@ -452,7 +658,7 @@ impl<'core> Frame<'core> {
         assert!(plane < self.format().plane_count());
 
         let stride = self.stride(plane);
-        let width = self.width(plane) * usize::from(self.format().bytes_per_sample());
+        let width = self.width(plane) * self.bytes_per_sample();
         if stride != width {
             return Err(NonZeroPadding(stride - width));
         }
@@ -464,6 +670,18 @@ impl<'core> Frame<'core> {
         Ok(unsafe { slice::from_raw_parts(ptr, length) })
     }
 
+    /// Returns a slice of the plane's pixels if the plane has no padding.
+    ///
+    /// This is the fast path for exporting the plane data: when it returns `None`, the rows have to
+    /// be read one by one, or `plane_packed()` can be used instead.
+    ///
+    /// # Panics
+    /// Panics if `plane >= format().plane_count()`.
+    #[inline]
+    pub fn plane_contiguous(&self, plane: usize) -> Option<&[u8]> {
+        self.data(plane).ok()
+    }
+
     /// Returns a mutable slice of the plane's pixels.
     ///
     /// The length of the returned slice is `height() * width() * format().bytes_per_sample()`. If
@@ -476,7 +694,7 @@ impl<'core> Frame<'core> {
         assert!(plane < self.format().plane_count());
 
         let stride = self.stride(plane);
-        let width = self.width(plane) * usize::from(self.format().bytes_per_sample());
+        let width = self.width(plane) * self.bytes_per_sample();
         if stride != width {
             return Err(NonZeroPadding(stride - width));
         }
@@ -488,6 +706,140 @@ impl<'core> Frame<'core> {
         Ok(unsafe { slice::from_raw_parts_mut(ptr, length) })
     }
 
+    /// Returns a new frame containing the absolute per-pixel difference between this frame and
+    /// `other`.
+    ///
+    /// Both frames must have the same format and resolution. The frame properties are copied from
+    /// this frame. Half precision float formats are only supported with the `f16-pixel-type`
+    /// feature.
+    pub fn difference(
+        &self,
+        other: &Frame<'core>,
+        core: CoreRef<'core>,
+    ) -> Result<FrameRefMut<'core>, Error> {
+        if self.format() != other.format() {
+            return Err(Error::FormatMismatch);
+        }
+        if self.resolution(0) != other.resolution(0) {
+            return Err(Error::ResolutionMismatch);
+        }
+
+        let mut rv = FrameRefMut::copy_of(core, self);
+
+        fn abs_diff<T: Component + Copy + PartialOrd + std::ops::Sub<Output = T>>(
+            dst: &mut Frame,
+            a: &Frame,
+            b: &Frame,
+        ) {
+            for plane in 0..a.format().plane_count() {
+                for row in 0..a.height(plane) {
+                    let a = a.plane_row::<T>(plane, row);
+                    let b = b.plane_row::<T>(plane, row);
+                    let dst = dst.plane_row_mut::<T>(plane, row);
+
+                    for ((dst, &a), &b) in dst.iter_mut().zip(a).zip(b) {
+                        // Subtracting the smaller value from the larger one can't overflow.
+                        *dst = if a > b { a - b } else { b - a };
+                    }
+                }
+            }
+        }
+
+        match (self.format().sample_type(), self.bytes_per_sample()) {
+            (SampleType::Integer, 1) => abs_diff::<u8>(&mut rv, self, other),
+            (SampleType::Integer, 2) => abs_diff::<u16>(&mut rv, self, other),
+            (SampleType::Integer, 4) => abs_diff::<u32>(&mut rv, self, other),
+            #[cfg(feature = "f16-pixel-type")]
+            (SampleType::Float, 2) => abs_diff::<f16>(&mut rv, self, other),
+            (SampleType::Float, 4) => abs_diff::<f32>(&mut rv, self, other),
+            (SampleType::Float, 8) => abs_diff::<f64>(&mut rv, self, other),
+            _ => return Err(Error::UnsupportedFormat),
+        }
+
+        Ok(rv)
+    }
+
+    /// Checks whether every pixel of this frame differs from the corresponding pixel of `other` by
+    /// at most `tolerance`.
+    ///
+    /// Both frames must have the same format and resolution. The row padding is ignored. For float
+    /// formats `tolerance` is compared with the absolute difference of the samples, and NaN
+    /// samples are never considered equal. Half precision float formats are only supported with
+    /// the `f16-pixel-type` feature.
+    pub fn approx_equal(&self, other: &Frame, tolerance: u32) -> Result<bool, Error> {
+        if self.format() != other.format() {
+            return Err(Error::FormatMismatch);
+        }
+        if self.resolution(0) != other.resolution(0) {
+            return Err(Error::ResolutionMismatch);
+        }
+
+        fn within_tolerance<T: Component + Copy + Into<f64>>(
+            a: &Frame,
+            b: &Frame,
+            tolerance: f64,
+        ) -> bool {
+            (0..a.format().plane_count()).all(|plane| {
+                (0..a.height(plane)).all(|row| {
+                    let a = a.plane_row::<T>(plane, row);
+                    let b = b.plane_row::<T>(plane, row);
+
+                    a.iter()
+                        .zip(b)
+                        .all(|(&a, &b)| (a.into() - b.into()).abs() <= tolerance)
+                })
+            })
+        }
+
+        let tolerance = f64::from(tolerance);
+        Ok(
+            match (self.format().sample_type(), self.bytes_per_sample()) {
+                (SampleType::Integer, 1) => within_tolerance::<u8>(self, other, tolerance),
+                (SampleType::Integer, 2) => within_tolerance::<u16>(self, other, tolerance),
+                (SampleType::Integer, 4) => within_tolerance::<u32>(self, other, tolerance),
+                #[cfg(feature = "f16-pixel-type")]
+                (SampleType::Float, 2) => within_tolerance::<f16>(self, other, tolerance),
+                (SampleType::Float, 4) => within_tolerance::<f32>(self, other, tolerance),
+                (SampleType::Float, 8) => within_tolerance::<f64>(self, other, tolerance),
+                _ => return Err(Error::UnsupportedFormat),
+            },
+        )
+    }
+
+    /// Returns the presentation time of the frame in seconds, read from the `_AbsoluteTime`
+    /// property.
+    ///
+    /// Returns `None` if the property isn't set. Some source filters set it for clips with
+    /// variable frame rate.
+    #[inline]
+    pub fn absolute_time(&self) -> Option<f64> {
+        self.props().get_float("_AbsoluteTime").ok()
+    }
+
+    /// Returns whether the frame is combed, read from the `_Combed` property.
+    ///
+    /// Returns `None` if the property isn't set. Combing detection filters like
+    /// `tdm.IsCombed` set it.
+    #[inline]
+    pub fn combed(&self) -> Option<bool> {
+        self.props().get_int("_Combed").ok().map(|x| x != 0)
+    }
+
+    /// Returns the field the frame was made from, read from the `_Field` property.
+    ///
+    /// Returns `None` if the property isn't set. Filters which separate or double the fields set
+    /// it to 0 for the bottom field and to 1 for the top field.
+    #[inline]
+    pub fn field(&self) -> Option<i64> {
+        self.props().get_int("_Field").ok()
+    }
+
+    /// Returns `true` if the frame has the property with the specified key.
+    #[inline]
+    pub fn has_prop(&self, key: &str) -> bool {
+        self.props().contains_key(key)
+    }
+
     /// Returns a map of frame's properties.
     #[inline]
     pub fn props(&self) -> MapRef {
@@ -4,12 +4,13 @@ use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 use std::os::raw::c_void;
 use std::ptr::NonNull;
+use std::sync::Mutex;
 use std::{mem, panic, process};
 use vapoursynth_sys as ffi;
 
 use crate::api::API;
 use crate::core::CoreRef;
-use crate::map::{Map, MapRef, MapRefMut};
+use crate::map::{Map, MapRef, MapRefMut, OwnedMap};
 
 /// Holds a reference to a function that may be called.
 #[derive(Debug)]
@@ -115,6 +116,26 @@ impl<'core> Function<'core> {
         }
     }
 
+    /// Creates a new function from a closure which takes the arguments and returns the results.
+    ///
+    /// This can be used to pass Rust logic to filters which take functions, like `std.FrameEval`.
+    /// The returned map is copied into the output map of the call, so to indicate an error, set it
+    /// on the returned map with `set_error()`.
+    ///
+    /// VapourSynth may call the function from several threads at once, so the calls are
+    /// serialized. The closure is dropped when the last reference to the function is freed.
+    pub fn from_closure<F>(api: API, core: CoreRef<'core>, callback: F) -> Self
+    where
+        F: FnMut(&Map<'core>) -> OwnedMap<'core> + Send + 'core,
+    {
+        let callback = Mutex::new(callback);
+
+        Self::new(api, core, move |_, _, in_, out| {
+            let rv = (*callback.lock().unwrap())(in_);
+            rv.copy_into(out);
+        })
+    }
+
     /// Calls the function. If the call fails `out` will have an error set.
     #[inline]
     pub fn call(&self, in_: &Map<'core>, out: &mut Map<'core>) {
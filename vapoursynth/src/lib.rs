@@ -7,7 +7,8 @@
 //!
 //! Most of the VapourSynth API is covered. It's possible to evaluate `.vpy` scripts, access their
 //! properties and output, retrieve frames; enumerate loaded plugins and invoke their functions as
-//! well as create VapourSynth filters.
+//! well as create VapourSynth filters. The `render` module writes clips out as raw or YUV4MPEG2
+//! streams, like vspipe does.
 //!
 //! For an example usage see
 //! [examples/vspipe.rs](https://github.com/YaLTeR/vapoursynth-rs/blob/master/vapoursynth/examples/vspipe.rs),
@@ -177,6 +178,7 @@ extern crate vapoursynth_sys;
 pub mod vsscript;
 
 pub mod api;
+pub mod clip;
 pub mod component;
 pub mod core;
 pub mod format;
@@ -186,6 +188,7 @@ pub mod map;
 pub mod node;
 pub mod plugin;
 pub mod plugins;
+pub mod render;
 pub mod video_info;
 
 pub mod prelude {
@@ -21,6 +21,8 @@ mod iterators;
 pub use self::iterators::{Keys, ValueIter};
 
 mod value;
+#[doc(hidden)]
+pub use self::value::MacroValue;
 pub use self::value::{Value, ValueType};
 
 /// A VapourSynth map.
@@ -151,6 +153,16 @@ impl<'elem> OwnedMap<'elem> {
             map: Map::from_ptr(handle),
         }
     }
+
+    /// Creates a new map with the given values, used by the `vsmap!` macro.
+    #[doc(hidden)]
+    pub fn from_macro_values(api: API, values: &[(&str, &dyn MacroValue)]) -> Result<Self> {
+        let mut map = Self::new(api);
+        for &(key, value) in values {
+            value.set_in_map(&mut map, key)?;
+        }
+        Ok(map)
+    }
 }
 
 impl<'owner, 'elem> MapRef<'owner, 'elem> {
@@ -340,6 +352,14 @@ impl<'elem> Map<'elem> {
         unsafe { self.value_count_raw_unchecked(&key) }
     }
 
+    /// Returns `true` if the map contains a value for the specified key.
+    ///
+    /// Invalid keys are never contained in a map, so `false` is returned for them.
+    #[inline]
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.value_count(key).is_ok()
+    }
+
     /// Retrieves a value type from a map.
     ///
     /// # Safety
@@ -428,6 +448,62 @@ impl<'elem> Map<'elem> {
         Ok(())
     }
 
+    /// Copies all keys and values of this map into a new `OwnedMap`.
+    ///
+    /// Nodes, frames and functions are reference counted, so the new map shares them with this one
+    /// rather than duplicating them.
+    pub fn to_owned_map(&self) -> OwnedMap<'elem> {
+        // The API is cached since this map exists.
+        let mut rv = OwnedMap::new(unsafe { API::get_cached() });
+        self.copy_into(&mut rv);
+        rv
+    }
+
+    /// Appends all keys and values of this map to `dst`.
+    ///
+    /// # Panics
+    /// Panics if `dst` has any of the keys with a different value type.
+    pub(crate) fn copy_into(&self, dst: &mut Map<'elem>) {
+        // The keys come from this map, so they are valid and have values of the given type.
+        for key in self.keys() {
+            let value_type = self.value_type(key).unwrap();
+            dst.touch(key, value_type).unwrap();
+
+            match value_type {
+                ValueType::Int => {
+                    for x in self.get_int_iter(key).unwrap() {
+                        dst.append_int(key, x).unwrap();
+                    }
+                }
+                ValueType::Float => {
+                    for x in self.get_float_iter(key).unwrap() {
+                        dst.append_float(key, x).unwrap();
+                    }
+                }
+                ValueType::Data => {
+                    for x in self.get_data_iter(key).unwrap() {
+                        dst.append_data(key, x).unwrap();
+                    }
+                }
+                ValueType::Node => {
+                    for x in self.get_node_iter(key).unwrap() {
+                        dst.append_node(key, &x).unwrap();
+                    }
+                }
+                ValueType::Frame => {
+                    for x in self.get_frame_iter(key).unwrap() {
+                        dst.append_frame(key, &x).unwrap();
+                    }
+                }
+                ValueType::Function => {
+                    for x in self.get_function_iter(key).unwrap() {
+                        dst.append_function(key, &x).unwrap();
+                    }
+                }
+            }
+        }
+    }
+
     /// Retrieves a property value.
     #[inline]
     pub fn get<'map, T: Value<'map, 'elem>>(&'map self, key: &str) -> Result<T> {
@@ -481,6 +557,22 @@ impl<'elem> Map<'elem> {
         unsafe { self.get_int_array_raw_unchecked(&key) }
     }
 
+    /// Retrieves all integers associated with the key into a `Vec`.
+    ///
+    /// Unlike `get_int_array()`, the returned values don't borrow the map.
+    #[inline]
+    pub fn get_int_vec(&self, key: &str) -> Result<Vec<i64>> {
+        #[cfg(feature = "gte-vapoursynth-api-31")]
+        {
+            self.get_int_array(key).map(<[i64]>::to_vec)
+        }
+
+        #[cfg(not(feature = "gte-vapoursynth-api-31"))]
+        {
+            self.get_int_iter(key).map(Iterator::collect)
+        }
+    }
+
     /// Retrieves a floating point number from a map.
     ///
     /// This function retrieves the first value associated with the key.
@@ -500,6 +592,22 @@ impl<'elem> Map<'elem> {
         unsafe { self.get_float_array_raw_unchecked(&key) }
     }
 
+    /// Retrieves all floating point numbers associated with the key into a `Vec`.
+    ///
+    /// Unlike `get_float_array()`, the returned values don't borrow the map.
+    #[inline]
+    pub fn get_float_vec(&self, key: &str) -> Result<Vec<f64>> {
+        #[cfg(feature = "gte-vapoursynth-api-31")]
+        {
+            self.get_float_array(key).map(<[f64]>::to_vec)
+        }
+
+        #[cfg(not(feature = "gte-vapoursynth-api-31"))]
+        {
+            self.get_float_iter(key).map(Iterator::collect)
+        }
+    }
+
     /// Retrieves floating point numbers from a map.
     #[inline]
     pub fn get_float_iter<'map>(&'map self, key: &str) -> Result<ValueIter<'map, 'elem, f64>> {
@@ -526,6 +634,13 @@ impl<'elem> Map<'elem> {
         unsafe { ValueIter::<&[u8]>::new(self, key) }
     }
 
+    /// Retrieves all data associated with the key into a `Vec` of owned copies.
+    #[inline]
+    pub fn get_data_vec(&self, key: &str) -> Result<Vec<Vec<u8>>> {
+        self.get_data_iter(key)
+            .map(|iter| iter.map(<[u8]>::to_vec).collect())
+    }
+
     /// Retrieves a node from a map.
     ///
     /// This function retrieves the first value associated with the key.
@@ -1058,3 +1173,39 @@ impl<'elem> Map<'elem> {
         debug_assert!(error == 0);
     }
 }
+
+/// Creates an `OwnedMap` with the given keys and values.
+///
+/// The value types are inferred from the literals: integers are stored as ints, floating point
+/// numbers as floats and strings as data. Evaluates to a `map::Result<OwnedMap>`.
+///
+/// The API can be passed before a semicolon. Otherwise it's retrieved with `API::get()`, which
+/// requires the `vapoursynth-functions` or the `vsscript-functions` feature, and the macro panics
+/// if the API can't be retrieved.
+///
+/// # Example
+/// ```no_run
+/// # #[macro_use] extern crate vapoursynth;
+/// # use vapoursynth::prelude::*;
+/// # fn f(api: API) -> Result<(), vapoursynth::map::Error> {
+/// let args = vsmap!(api; "width" => 1920i64, "fps" => 23.976, "name" => "clip")?;
+/// assert_eq!(args.get_int("width"), Ok(1920));
+/// # Ok(())
+/// # }
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! vsmap {
+    ($api:expr; $($key:expr => $value:expr),* $(,)?) => {
+        $crate::map::OwnedMap::from_macro_values(
+            $api,
+            &[$(($key, &$value as &dyn $crate::map::MacroValue)),*],
+        )
+    };
+    ($($key:expr => $value:expr),* $(,)?) => {
+        $crate::vsmap!(
+            $crate::api::API::get().expect("Couldn't retrieve the VapourSynth API");
+            $($key => $value),*
+        )
+    };
+}
@@ -159,3 +159,39 @@ impl<'map, 'elem: 'map> Value<'map, 'elem> for Function<'elem> {
         map.append_function(key, x)
     }
 }
+
+/// A trait for the literal values accepted by the `vsmap!` macro.
+#[doc(hidden)]
+pub trait MacroValue {
+    /// Sets the property value in the map.
+    fn set_in_map(&self, map: &mut Map, key: &str) -> Result<()>;
+}
+
+impl MacroValue for i64 {
+    #[inline]
+    fn set_in_map(&self, map: &mut Map, key: &str) -> Result<()> {
+        map.set_int(key, *self)
+    }
+}
+
+// Unsuffixed integer literals default to i32.
+impl MacroValue for i32 {
+    #[inline]
+    fn set_in_map(&self, map: &mut Map, key: &str) -> Result<()> {
+        map.set_int(key, i64::from(*self))
+    }
+}
+
+impl MacroValue for f64 {
+    #[inline]
+    fn set_in_map(&self, map: &mut Map, key: &str) -> Result<()> {
+        map.set_float(key, *self)
+    }
+}
+
+impl MacroValue for &str {
+    #[inline]
+    fn set_in_map(&self, map: &mut Map, key: &str) -> Result<()> {
+        map.set_data(key, self.as_bytes())
+    }
+}
@@ -1,20 +1,43 @@
 use std::borrow::Cow;
-use std::error::Error;
 use std::ffi::CStr;
 use std::fmt;
 
+use thiserror::Error;
+
+/// The error type for `Node` operations.
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum Error {
+    #[error("Couldn't get frame {frame}: {message}")]
+    GetFrame { frame: usize, message: String },
+    #[error("The clip length is unknown")]
+    UnknownLength,
+}
+
+impl<'a> From<GetFrameError<'a>> for Error {
+    #[inline]
+    fn from(x: GetFrameError<'a>) -> Self {
+        Error::GetFrame {
+            frame: x.frame(),
+            message: x.into_inner().to_string_lossy().into_owned(),
+        }
+    }
+}
+
 /// A container for a `get_frame` error.
 #[derive(Debug)]
-pub struct GetFrameError<'a>(Cow<'a, CStr>);
+pub struct GetFrameError<'a> {
+    frame: usize,
+    message: Cow<'a, CStr>,
+}
 
 impl<'a> fmt::Display for GetFrameError<'a> {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.0.to_string_lossy())
+        write!(f, "{}", self.message.to_string_lossy())
     }
 }
 
-impl<'a> Error for GetFrameError<'a> {
+impl<'a> std::error::Error for GetFrameError<'a> {
     #[inline]
     fn description(&self) -> &str {
         "VapourSynth error"
@@ -22,15 +45,30 @@ impl<'a> Error for GetFrameError<'a> {
 }
 
 impl<'a> GetFrameError<'a> {
-    /// Creates a new `GetFrameError` with the given error message.
+    /// Creates a new `GetFrameError` for the frame number `frame` with the given error message.
+    #[inline]
+    pub(crate) fn new(frame: usize, message: Cow<'a, CStr>) -> Self {
+        GetFrameError { frame, message }
+    }
+
+    /// Converts the error message into an owned one.
+    #[inline]
+    pub(crate) fn into_owned(self) -> GetFrameError<'static> {
+        GetFrameError {
+            frame: self.frame,
+            message: Cow::Owned(self.message.into_owned()),
+        }
+    }
+
+    /// Returns the number of the frame that couldn't be retrieved.
     #[inline]
-    pub(crate) fn new(message: Cow<'a, CStr>) -> Self {
-        GetFrameError(message)
+    pub fn frame(&self) -> usize {
+        self.frame
     }
 
     /// Consumes this error, returning its underlying error message.
     #[inline]
     pub fn into_inner(self) -> Cow<'a, CStr> {
-        self.0
+        self.message
     }
 }
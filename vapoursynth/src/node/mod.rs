@@ -1,22 +1,33 @@
 //! VapourSynth nodes.
 
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::ffi::{CStr, CString};
+use std::fmt::{self, Write};
 use std::marker::PhantomData;
+use std::ops::Range;
 use std::os::raw::{c_char, c_void};
 use std::process;
 use std::ptr::NonNull;
-use std::{mem, panic};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::{iter, mem, panic};
 use vapoursynth_sys as ffi;
 
 use crate::api::API;
+use crate::format::ColorFamily;
 use crate::frame::FrameRef;
 use crate::plugins::FrameContext;
 use crate::prelude::Property;
 use crate::video_info::VideoInfo;
 
 mod errors;
-pub use self::errors::GetFrameError;
+pub use self::errors::{Error, GetFrameError};
+
+/// The error message buffer capacity used by `Node::get_frame()`, in bytes.
+// Kinda arbitrary. Same value as used in vsvfw.
+pub const DEFAULT_ERROR_BUF_CAPACITY: usize = 32 * 1024;
 
 bitflags! {
     /// Node flags.
@@ -44,6 +55,179 @@ impl From<ffi::VSNodeFlags> for Flags {
     }
 }
 
+/// A handle to a frame request made with `Node::get_frame_async_cancellable()`.
+///
+/// Dropping the handle or calling `cancel()` signals that the caller is no longer interested in
+/// the frame: the callback won't be called and the frame will be freed as soon as it arrives.
+///
+/// VapourSynth has no way of cancelling a request that's already in flight, so the frame is still
+/// generated; this only lets the consumer skip processing it.
+#[derive(Debug)]
+#[must_use = "dropping the handle cancels the request"]
+pub struct RequestHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl RequestHandle {
+    /// Signals that the requested frame is no longer needed.
+    #[inline]
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the request was cancelled.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for RequestHandle {
+    #[inline]
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// The item type of `Stream`.
+pub type StreamItem<'core> = Result<(usize, FrameRef<'core>), GetFrameError<'static>>;
+
+// Requests frame `n` of `node` and sends it into `sender` once it's generated.
+//
+// The callback never blocks: if the receiver was dropped, the frame is freed right away.
+fn request_into<'core>(node: &Node<'core>, n: usize, sender: Sender<StreamItem<'core>>) {
+    node.get_frame_async(n, move |frame, n, _node| {
+        let item = frame
+            .map(|frame| (n, frame))
+            .map_err(GetFrameError::into_owned);
+        let _ = sender.send(item);
+    });
+}
+
+/// A stream of frames of a node, in the order they are generated.
+///
+/// This `struct` is created by the `stream()` method on `Node`.
+#[derive(Debug)]
+pub struct Stream<'core> {
+    node: Node<'core>,
+    sender: Sender<StreamItem<'core>>,
+    receiver: Receiver<StreamItem<'core>>,
+    // The next frame number to request.
+    next: usize,
+    end: usize,
+    // The number of requested frames which weren't received yet.
+    outstanding: usize,
+}
+
+impl<'core> Stream<'core> {
+    // Requests the next frame, if any.
+    fn request_next(&mut self) {
+        if self.next < self.end {
+            request_into(&self.node, self.next, self.sender.clone());
+            self.next += 1;
+            self.outstanding += 1;
+        }
+    }
+
+    /// Blocks until a requested frame is generated and returns it together with its number, then
+    /// requests the next frame.
+    ///
+    /// Returns `None` once all frames were received.
+    pub fn recv(&mut self) -> Option<StreamItem<'core>> {
+        if self.outstanding == 0 {
+            return None;
+        }
+
+        // The stream holds a sender, so this can't fail.
+        let item = self.receiver.recv().unwrap();
+        self.outstanding -= 1;
+        self.request_next();
+
+        Some(item)
+    }
+}
+
+impl<'core> Iterator for Stream<'core> {
+    type Item = StreamItem<'core>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.outstanding + (self.end - self.next);
+        (len, Some(len))
+    }
+}
+
+impl<'core> ExactSizeIterator for Stream<'core> {}
+
+/// An iterator over the frames of a node, in order.
+///
+/// This `struct` is created by the `frames()` method on `Node`.
+#[derive(Debug)]
+pub struct Frames<'core> {
+    node: Node<'core>,
+    sender: Sender<StreamItem<'core>>,
+    receiver: Receiver<StreamItem<'core>>,
+    // Frames which arrived before the frames preceding them.
+    pending: HashMap<usize, StreamItem<'core>>,
+    requests: usize,
+    // The next frame number to request.
+    next_request: usize,
+    // The next frame number to return.
+    next: usize,
+    end: usize,
+}
+
+impl<'core> Frames<'core> {
+    // Requests the frames up to the end of the window. Every frame between `next` and
+    // `next_request` is either in flight or pending, so this keeps their number within `requests`.
+    fn fill_window(&mut self) {
+        let window_end = self.end.min(self.next + self.requests);
+
+        while self.next_request < window_end {
+            request_into(&self.node, self.next_request, self.sender.clone());
+            self.next_request += 1;
+        }
+    }
+}
+
+impl<'core> Iterator for Frames<'core> {
+    type Item = Result<FrameRef<'core>, GetFrameError<'static>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.end {
+            return None;
+        }
+
+        loop {
+            if let Some(item) = self.pending.remove(&self.next) {
+                self.next += 1;
+                self.fill_window();
+                return Some(item.map(|(_, frame)| frame));
+            }
+
+            let item = self.receiver.recv().ok()?;
+            let n = match item {
+                Ok((n, _)) => n,
+                Err(ref error) => error.frame(),
+            };
+            self.pending.insert(n, item);
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.next;
+        (len, Some(len))
+    }
+}
+
+impl<'core> ExactSizeIterator for Frames<'core> {}
+
 /// A reference to a node in the constructed filter graph.
 #[derive(Debug)]
 pub struct Node<'core> {
@@ -51,6 +235,9 @@ pub struct Node<'core> {
     _owner: PhantomData<&'core ()>,
 }
 
+// SAFETY: the VapourSynth node functions are thread-safe: frames can be requested from any thread
+// (the asynchronous frame callbacks are invoked on the worker threads), the video info is
+// immutable once the node is created, and node references are atomically reference counted.
 unsafe impl<'core> Send for Node<'core> {}
 unsafe impl<'core> Sync for Node<'core> {}
 
@@ -104,14 +291,97 @@ impl<'core> Node<'core> {
         }
     }
 
+    /// Returns a multi-line description of the node's video info, in the same format as vspipe's
+    /// `--info` output.
+    ///
+    /// `alpha` is the alpha node of the output, if any; its presence is reported on the `Alpha`
+    /// line. Use the `Display` implementation of `VideoInfo` for a one-line summary.
+    pub fn get_info_string(&self, alpha: Option<&Node<'core>>) -> String {
+        let info = self.info();
+        let mut rv = String::new();
+
+        // Writing into a String never fails.
+        let mut line = |name: &str, value: &dyn fmt::Display| {
+            let _ = writeln!(rv, "{}: {}", name, value);
+        };
+
+        match info.resolution {
+            Property::Variable => {
+                line("Width", &"Variable");
+                line("Height", &"Variable");
+            }
+            Property::Constant(resolution) => {
+                line("Width", &resolution.width);
+                line("Height", &resolution.height);
+            }
+        }
+
+        #[cfg(feature = "gte-vapoursynth-api-32")]
+        line("Frames", &info.num_frames);
+
+        #[cfg(not(feature = "gte-vapoursynth-api-32"))]
+        match info.num_frames {
+            Property::Variable => line("Frames", &"Unknown"),
+            Property::Constant(num_frames) => line("Frames", &num_frames),
+        }
+
+        match info.framerate {
+            Property::Variable => line("FPS", &"Variable"),
+            Property::Constant(framerate) => line(
+                "FPS",
+                &format!(
+                    "{} ({:.3} fps)",
+                    framerate,
+                    framerate.numerator as f64 / framerate.denominator as f64
+                ),
+            ),
+        }
+
+        match info.format {
+            Property::Variable => line("Format Name", &"Variable"),
+            Property::Constant(format) => {
+                line("Format Name", &format.name());
+                line("Color Family", &format.color_family());
+                line("Alpha", &if alpha.is_some() { "Yes" } else { "No" });
+                line("Sample Type", &format.sample_type());
+                line("Bits", &format.bits_per_sample());
+                line("SubSampling W", &format.sub_sampling_w());
+                line("SubSampling H", &format.sub_sampling_h());
+            }
+        }
+
+        rv
+    }
+
     /// Generates a frame directly.
     ///
     /// The `'error` lifetime is unbounded because this function always returns owned data.
     ///
+    /// Error messages are truncated to `DEFAULT_ERROR_BUF_CAPACITY - 1` bytes. Use
+    /// `get_frame_with_errbuf()` to retrieve longer messages.
+    ///
     /// # Panics
     /// Panics is `n` is greater than `i32::max_value()`.
+    #[inline]
     pub fn get_frame<'error>(&self, n: usize) -> Result<FrameRef<'core>, GetFrameError<'error>> {
+        self.get_frame_with_errbuf(n, DEFAULT_ERROR_BUF_CAPACITY)
+    }
+
+    /// Generates a frame directly, with an error message buffer of `capacity` bytes.
+    ///
+    /// VapourSynth truncates error messages which don't fit into the buffer, so the message is
+    /// at most `capacity - 1` bytes long, not counting the terminating null byte.
+    ///
+    /// # Panics
+    /// Panics is `n` is greater than `i32::max_value()`, or if `capacity` is zero or greater than
+    /// `i32::max_value()`.
+    pub fn get_frame_with_errbuf<'error>(
+        &self,
+        n: usize,
+        capacity: usize,
+    ) -> Result<FrameRef<'core>, GetFrameError<'error>> {
         assert!(n <= i32::max_value() as usize);
+        assert!(capacity > 0);
 
         let vi = &self.info();
 
@@ -120,20 +390,17 @@ impl<'core> Node<'core> {
             if n >= total {
                 let err_cstring =
                     CString::new("Requested frame number beyond the last one").unwrap();
-                return Err(GetFrameError::new(Cow::Owned(err_cstring)));
+                return Err(GetFrameError::new(n, Cow::Owned(err_cstring)));
             }
         }
 
         #[cfg(feature = "gte-vapoursynth-api-32")]
         if n >= vi.num_frames {
             let err_cstring = CString::new("Requested frame number beyond the last one").unwrap();
-            return Err(GetFrameError::new(Cow::Owned(err_cstring)));
+            return Err(GetFrameError::new(n, Cow::Owned(err_cstring)));
         }
 
-        // Kinda arbitrary. Same value as used in vsvfw.
-        const ERROR_BUF_CAPACITY: usize = 32 * 1024;
-
-        let mut err_buf = vec![0; ERROR_BUF_CAPACITY];
+        let mut err_buf = vec![0; capacity];
         let mut err_buf = err_buf.into_boxed_slice();
 
         let handle =
@@ -142,7 +409,7 @@ impl<'core> Node<'core> {
         if handle.is_null() {
             // TODO: remove this extra allocation by reusing `Box<[c_char]>`.
             let error = unsafe { CStr::from_ptr(err_buf.as_ptr()) }.to_owned();
-            Err(GetFrameError::new(Cow::Owned(error)))
+            Err(GetFrameError::new(n, Cow::Owned(error)))
         } else {
             Ok(unsafe { FrameRef::from_ptr(handle) })
         }
@@ -208,10 +475,13 @@ impl<'core> Node<'core> {
             let user_data = Box::from_raw(user_data as *mut CallbackData<'static>);
 
             let closure = panic::AssertUnwindSafe(move || {
+                debug_assert!(n >= 0);
+                let n = n as usize;
+
                 let frame = if frame.is_null() {
                     debug_assert!(!error_msg.is_null());
                     let error_msg = Cow::Borrowed(CStr::from_ptr(error_msg));
-                    Err(GetFrameError::new(error_msg))
+                    Err(GetFrameError::new(n, error_msg))
                 } else {
                     debug_assert!(error_msg.is_null());
                     Ok(FrameRef::from_ptr(frame))
@@ -219,9 +489,6 @@ impl<'core> Node<'core> {
 
                 let node = Node::from_ptr(node);
 
-                debug_assert!(n >= 0);
-                let n = n as usize;
-
                 user_data.callback.call(frame, n, node);
             });
 
@@ -252,6 +519,183 @@ impl<'core> Node<'core> {
         mem::forget(new_node);
     }
 
+    /// Requests the generation of a frame, returning a handle which can be used to signal that
+    /// the frame is no longer needed.
+    ///
+    /// This works like `get_frame_async()`, except that `callback` is only called if the request
+    /// wasn't cancelled by the time the frame arrives. See `RequestHandle` for details.
+    ///
+    /// # Panics
+    /// Panics is `n` is greater than `i32::max_value()`.
+    pub fn get_frame_async_cancellable<F>(&self, n: usize, callback: F) -> RequestHandle
+    where
+        F: FnOnce(Result<FrameRef<'core>, GetFrameError>, usize, Node<'core>) + Send + 'core,
+    {
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let cancelled_2 = cancelled.clone();
+        self.get_frame_async(n, move |frame, n, node| {
+            if !cancelled_2.load(Ordering::Relaxed) {
+                callback(frame, n, node);
+            }
+        });
+
+        RequestHandle { cancelled }
+    }
+
+    /// Requests the frames in `range` and returns them as they are generated.
+    ///
+    /// Up to `requests` frames are requested concurrently. The frames are returned together with
+    /// their numbers in the order they are generated, which isn't necessarily the order of the
+    /// frame numbers. A new frame is only requested when a generated frame is taken out of the
+    /// stream, so at most `requests` frames are in flight or waiting at any time. The frame
+    /// callbacks never block the VapourSynth worker threads.
+    ///
+    /// Dropping the stream stops further requests. Frames that are already in flight are still
+    /// generated, but they are freed right away.
+    ///
+    /// # Panics
+    /// Panics if `requests` is zero or if `range.end` is greater than `i32::max_value() + 1`.
+    pub fn stream(&self, range: Range<usize>, requests: usize) -> Stream<'core> {
+        assert!(requests > 0);
+        assert!(range.end <= i32::max_value() as usize + 1);
+
+        let (sender, receiver) = mpsc::channel();
+        let mut stream = Stream {
+            node: self.clone(),
+            sender,
+            receiver,
+            next: range.start,
+            end: range.end.max(range.start),
+            outstanding: 0,
+        };
+
+        for _ in 0..requests.min(range.len()) {
+            stream.request_next();
+        }
+
+        stream
+    }
+
+    /// Returns an iterator over the frames in `range`, in order.
+    ///
+    /// The frames are requested in a sliding window of `requests` frames, starting from the next
+    /// frame to return. Frames which arrive out of order are buffered until all frames preceding
+    /// them are returned, so at most `requests` frames are in flight or buffered at any time: a
+    /// slow frame holds back the requests instead of letting the buffer grow.
+    ///
+    /// Dropping the iterator stops further requests. Frames that are already in flight are still
+    /// generated, but they are freed right away.
+    ///
+    /// # Panics
+    /// Panics if `requests` is zero or if `range.end` is greater than `i32::max_value() + 1`.
+    pub fn frames(&self, range: Range<usize>, requests: usize) -> Frames<'core> {
+        assert!(requests > 0);
+        assert!(range.end <= i32::max_value() as usize + 1);
+
+        let (sender, receiver) = mpsc::channel();
+        let mut frames = Frames {
+            node: self.clone(),
+            sender,
+            receiver,
+            pending: HashMap::new(),
+            requests,
+            next_request: range.start,
+            next: range.start,
+            end: range.end,
+        };
+        frames.fill_window();
+        frames
+    }
+
+    /// Returns an iterator over the data of the frames in `range`, in order.
+    ///
+    /// Each item contains all planes of a frame with the row padding removed, one after another.
+    /// For RGB formats the planes are output in the G, B, R order, like vspipe does.
+    ///
+    /// The frames are requested like in `frames()`.
+    ///
+    /// # Panics
+    /// Panics if `requests` is zero or if `range.end` is greater than `i32::max_value() + 1`.
+    pub fn packed_frames(
+        &self,
+        range: Range<usize>,
+        requests: usize,
+    ) -> impl Iterator<Item = Result<Vec<u8>, Error>> + 'core {
+        const RGB_REMAP: [usize; 3] = [1, 2, 0];
+
+        self.frames(range, requests).map(|frame| {
+            let frame = frame?;
+            let format = frame.format();
+
+            let resolution = frame.resolution(0);
+            let mut rv =
+                Vec::with_capacity(format.frame_size_bytes(resolution.width, resolution.height));
+
+            let planes = (0..format.plane_count()).map(|plane| {
+                if format.color_family() == ColorFamily::RGB {
+                    RGB_REMAP[plane]
+                } else {
+                    plane
+                }
+            });
+
+            for plane in planes {
+                for row in frame.rows(plane) {
+                    rv.extend_from_slice(row);
+                }
+            }
+
+            Ok(rv)
+        })
+    }
+
+    /// Returns an iterator over all frames of the clip in groups of `chunk_size` consecutive
+    /// frames, in order. The last chunk can be shorter.
+    ///
+    /// The frames are requested like in `frames()`. Memory usage scales with `chunk_size`: a whole
+    /// chunk is held before it's returned, in addition to the up to `requests` frames in flight or
+    /// buffered.
+    ///
+    /// If the clip length is unknown, the iterator returns a single `Error::UnknownLength`. The
+    /// iteration stops after the first error.
+    ///
+    /// # Panics
+    /// Panics if `chunk_size` or `requests` is zero.
+    pub fn chunks(
+        &self,
+        chunk_size: usize,
+        requests: usize,
+    ) -> impl Iterator<Item = Result<Vec<FrameRef<'core>>, Error>> + 'core {
+        assert!(chunk_size > 0);
+
+        let mut frames = self
+            .info()
+            .frame_count()
+            .map(|num_frames| self.frames(0..num_frames, requests));
+        let mut error = frames.is_none().then_some(Error::UnknownLength);
+
+        iter::from_fn(move || {
+            if let Some(error) = error.take() {
+                return Some(Err(error));
+            }
+
+            let chunk = frames
+                .as_mut()?
+                .take(chunk_size)
+                .collect::<Result<Vec<_>, _>>();
+            match chunk {
+                Ok(chunk) if chunk.is_empty() => None,
+                Ok(chunk) => Some(Ok(chunk)),
+                Err(error) => {
+                    // Stop after the first error.
+                    frames = None;
+                    Some(Err(error.into()))
+                }
+            }
+        })
+    }
+
     /// Requests a frame from a node and returns immediately.
     ///
     /// This is only used in filters' "get frame" functions.
@@ -0,0 +1,213 @@
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+/// The error type for `ArgSpecBuilder`.
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum ArgSpecError {
+    #[error("Invalid argument name: {}", _0)]
+    InvalidName(String),
+    #[error("Duplicate argument name: {}", _0)]
+    DuplicateName(String),
+    #[error("Argument {} is allowed to be empty but isn't an array", _0)]
+    EmptyNonArray(String),
+}
+
+/// Filter function argument types.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ArgType {
+    Int,
+    Float,
+    Data,
+    Clip,
+    Frame,
+    Func,
+}
+
+#[derive(Debug, Clone)]
+struct Arg {
+    name: String,
+    type_: ArgType,
+    array: bool,
+    optional: bool,
+    empty: bool,
+}
+
+/// A builder for filter function argument strings.
+///
+/// Arguments are added with the type-named methods. The `array()`, `optional()` and `empty()`
+/// methods modify the most recently added argument.
+///
+/// ```
+/// # use vapoursynth::plugins::{ArgSpecBuilder, ArgSpecError};
+/// let args = ArgSpecBuilder::new()
+///     .clip("clip")
+///     .float("threshold")
+///     .optional()
+///     .int("planes")
+///     .array()
+///     .optional()
+///     .build()
+///     .unwrap();
+/// assert_eq!(args, "clip:clip;threshold:float:opt;planes:int[]:opt;");
+///
+/// let duplicate = ArgSpecBuilder::new().clip("clip").int("clip").build();
+/// assert_eq!(duplicate, Err(ArgSpecError::DuplicateName("clip".to_owned())));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ArgSpecBuilder {
+    args: Vec<Arg>,
+}
+
+impl ArgType {
+    #[inline]
+    fn name(self) -> &'static str {
+        match self {
+            ArgType::Int => "int",
+            ArgType::Float => "float",
+            ArgType::Data => "data",
+            ArgType::Clip => "clip",
+            ArgType::Frame => "frame",
+            ArgType::Func => "func",
+        }
+    }
+}
+
+impl ArgSpecBuilder {
+    /// Creates an empty builder.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an argument of the given type.
+    #[inline]
+    pub fn arg(mut self, name: &str, type_: ArgType) -> Self {
+        self.args.push(Arg {
+            name: name.to_owned(),
+            type_,
+            array: false,
+            optional: false,
+            empty: false,
+        });
+        self
+    }
+
+    /// Adds an `int` argument.
+    #[inline]
+    pub fn int(self, name: &str) -> Self {
+        self.arg(name, ArgType::Int)
+    }
+
+    /// Adds a `float` argument.
+    #[inline]
+    pub fn float(self, name: &str) -> Self {
+        self.arg(name, ArgType::Float)
+    }
+
+    /// Adds a `data` argument.
+    #[inline]
+    pub fn data(self, name: &str) -> Self {
+        self.arg(name, ArgType::Data)
+    }
+
+    /// Adds a `clip` argument.
+    #[inline]
+    pub fn clip(self, name: &str) -> Self {
+        self.arg(name, ArgType::Clip)
+    }
+
+    /// Adds a `frame` argument.
+    #[inline]
+    pub fn frame(self, name: &str) -> Self {
+        self.arg(name, ArgType::Frame)
+    }
+
+    /// Adds a `func` argument.
+    #[inline]
+    pub fn func(self, name: &str) -> Self {
+        self.arg(name, ArgType::Func)
+    }
+
+    /// Returns the most recently added argument.
+    ///
+    /// # Panics
+    /// Panics if no arguments were added.
+    #[inline]
+    fn last(&mut self) -> &mut Arg {
+        self.args
+            .last_mut()
+            .expect("an argument must be added before modifying it")
+    }
+
+    /// Makes the most recently added argument an array.
+    ///
+    /// # Panics
+    /// Panics if no arguments were added.
+    #[inline]
+    pub fn array(mut self) -> Self {
+        self.last().array = true;
+        self
+    }
+
+    /// Makes the most recently added argument optional.
+    ///
+    /// # Panics
+    /// Panics if no arguments were added.
+    #[inline]
+    pub fn optional(mut self) -> Self {
+        self.last().optional = true;
+        self
+    }
+
+    /// Allows the most recently added argument, which must be an array, to be empty.
+    ///
+    /// # Panics
+    /// Panics if no arguments were added.
+    #[inline]
+    pub fn empty(mut self) -> Self {
+        self.last().empty = true;
+        self
+    }
+
+    /// Validates the arguments and returns the argument string.
+    ///
+    /// The names must match `^[a-zA-Z][a-zA-Z0-9_]*$` and be unique.
+    pub fn build(&self) -> Result<String, ArgSpecError> {
+        let mut names = HashSet::new();
+        let mut rv = String::new();
+
+        for arg in &self.args {
+            let mut chars = arg.name.chars();
+            let is_valid = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !is_valid {
+                return Err(ArgSpecError::InvalidName(arg.name.clone()));
+            }
+
+            if !names.insert(&arg.name) {
+                return Err(ArgSpecError::DuplicateName(arg.name.clone()));
+            }
+
+            if arg.empty && !arg.array {
+                return Err(ArgSpecError::EmptyNonArray(arg.name.clone()));
+            }
+
+            rv += &arg.name;
+            rv += ":";
+            rv += arg.type_.name();
+            if arg.array {
+                rv += "[]";
+            }
+            if arg.optional {
+                rv += ":opt";
+            }
+            if arg.empty {
+                rv += ":empty";
+            }
+            rv += ";";
+        }
+
+        Ok(rv)
+    }
+}
@@ -10,6 +10,9 @@ use crate::map::{self, Map, Value, ValueIter};
 use crate::node::Node;
 use crate::video_info::VideoInfo;
 
+mod arg_spec;
+pub use self::arg_spec::{ArgSpecBuilder, ArgSpecError, ArgType};
+
 mod frame_context;
 pub use self::frame_context::FrameContext;
 
@@ -73,6 +76,8 @@ pub trait FilterFunction: Send + Sync {
     ///
     /// The following example declares the arguments "blah", "moo", and "asdf":
     /// `blah:clip;moo:int[]:opt;asdf:float:opt;`
+    ///
+    /// `ArgSpecBuilder` can be used to build and validate the argument string.
     fn args(&self) -> &str;
 
     /// The callback for this filter function.
@@ -0,0 +1,97 @@
+use std::fmt::Write;
+use std::io;
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+#[cfg(feature = "vsscript-functions")]
+use crate::vsscript;
+use crate::{format, node};
+
+/// The error type for rendering.
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Cannot output clips with varying format")]
+    VariableFormat,
+    #[error("Cannot output clips with varying dimensions")]
+    VariableResolution,
+    #[error("Cannot output clips with varying framerate")]
+    VariableFramerate,
+    #[error("Cannot output clips with unknown length")]
+    UnknownLength,
+    #[error("Invalid range of frames to output specified: first: {start}, last: {end}, clip length: {num_frames}")]
+    InvalidRange {
+        start: usize,
+        end: usize,
+        num_frames: usize,
+    },
+    #[error("The range of frames to output is empty: {start}..{end}")]
+    EmptyRange { start: usize, end: usize },
+    #[error("Frame {frame} is out of range, clip length: {num_frames}")]
+    InvalidFrame { frame: usize, num_frames: usize },
+    #[error("The number of concurrent requests must be positive")]
+    ZeroRequests,
+    #[error("The maximum number of buffered frames must be positive")]
+    ZeroBufferedFrames,
+    #[error("Can't apply y4m headers to a clip with alpha")]
+    Y4mWithAlpha,
+    #[error("No y4m identifier exists for the current format")]
+    Y4mUnsupportedFormat(#[source] Option<format::Error>),
+    #[error("The size of frame {frame} doesn't match the y4m header")]
+    Y4mFrameMismatch { frame: usize },
+    #[error("Couldn't write the header")]
+    Header(#[source] io::Error),
+    #[error(transparent)]
+    GetFrame(#[from] node::Error),
+    #[error("Couldn't output frame {frame}")]
+    Output {
+        frame: usize,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Couldn't output the timecode of frame {frame}")]
+    Timecode {
+        frame: usize,
+        #[source]
+        source: io::Error,
+    },
+    #[error("The duration denominator of frame {frame} is zero")]
+    ZeroDurationDenominator { frame: usize },
+    #[error("The frames have already been rendered")]
+    AlreadyRendered,
+    #[error("Couldn't flush the output")]
+    Flush(#[source] io::Error),
+    #[cfg(feature = "vsscript-functions")]
+    #[error("Script evaluation failed")]
+    Script(#[source] vsscript::Error),
+    #[cfg(feature = "vsscript-functions")]
+    #[error("Couldn't get output {index}")]
+    GetOutput {
+        index: i32,
+        #[source]
+        source: vsscript::Error,
+    },
+    #[error("Couldn't create {}", path.display())]
+    CreateFile {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Couldn't render {} output(s):{}", .0.len(), describe_output_errors(.0))]
+    Outputs(Vec<(i32, Error)>),
+}
+
+// Lists the per-output errors, one per line, including their sources.
+fn describe_output_errors(errors: &[(i32, Error)]) -> String {
+    let mut rv = String::new();
+    for (index, error) in errors {
+        let _ = write!(rv, "\noutput {}: {}", index, error);
+
+        let mut source = std::error::Error::source(error);
+        while let Some(error) = source {
+            let _ = write!(rv, ": {}", error);
+            source = error.source();
+        }
+    }
+    rv
+}
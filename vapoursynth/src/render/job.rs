@@ -0,0 +1,236 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::render::{
+    render, Error, FrameOrder, ProgressReporter, RenderMode, RenderOptions, RenderStats,
+    StderrProgress, DEFAULT_BUFFER_SIZE,
+};
+use crate::vsscript::{Environment, EvalFlags};
+
+/// A builder for rendering an output of a script, from evaluation to the written frames.
+///
+/// The setters validate their arguments where possible; the first invalid argument is reported by
+/// `run()`, before the script is evaluated.
+///
+/// ```no_run
+/// # use vapoursynth::render::{Error, RenderJob};
+/// # fn f() -> Result<(), Error> {
+/// let stats = RenderJob::from_script("script.vpy")
+///     .output_index(0)
+///     .range(0..100)
+///     .requests(8)
+///     .y4m(true)
+///     .timecodes("timecodes.txt")
+///     .run(std::io::stdout())?;
+///
+/// eprintln!("Output {} frames in {:?}", stats.frames, stats.elapsed);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RenderJob {
+    script: PathBuf,
+    flags: EvalFlags,
+    output_index: i32,
+    range: Option<Range<usize>>,
+    requests: Option<usize>,
+    max_buffered_frames: Option<usize>,
+    mode: RenderMode,
+    reverse: bool,
+    y4m: bool,
+    progress: bool,
+    timecodes: Option<PathBuf>,
+    // The first error found by the setters.
+    error: Option<Error>,
+}
+
+impl RenderJob {
+    /// Creates a job rendering the script at `path`.
+    ///
+    /// By default, the script is evaluated with `EvalFlags::SET_WORKING_DIR`, all frames of
+    /// output 0 are rendered in order without headers, and the number of concurrent requests is
+    /// the number of threads of the core.
+    #[inline]
+    pub fn from_script<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            script: path.as_ref().to_path_buf(),
+            flags: EvalFlags::SET_WORKING_DIR,
+            output_index: 0,
+            range: None,
+            requests: None,
+            max_buffered_frames: None,
+            mode: RenderMode::Default,
+            reverse: false,
+            y4m: false,
+            progress: false,
+            timecodes: None,
+            error: None,
+        }
+    }
+
+    // Records the first error found by the setters.
+    #[inline]
+    fn fail(mut self, error: Error) -> Self {
+        self.error.get_or_insert(error);
+        self
+    }
+
+    /// Sets the flags the script is evaluated with.
+    #[inline]
+    pub fn eval_flags(mut self, flags: EvalFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Sets the index of the output to render.
+    #[inline]
+    pub fn output_index(mut self, index: i32) -> Self {
+        self.output_index = index;
+        self
+    }
+
+    /// Sets the range of frames to render. The range must not be empty.
+    ///
+    /// Whether the range fits the clip is only known once the script is evaluated.
+    #[inline]
+    pub fn range(mut self, range: Range<usize>) -> Self {
+        if range.start >= range.end {
+            return self.fail(Error::EmptyRange {
+                start: range.start,
+                end: range.end,
+            });
+        }
+
+        self.range = Some(range);
+        self
+    }
+
+    /// Sets the number of concurrent frame requests, must be positive.
+    #[inline]
+    pub fn requests(mut self, requests: usize) -> Self {
+        if requests == 0 {
+            return self.fail(Error::ZeroRequests);
+        }
+
+        self.requests = Some(requests);
+        self
+    }
+
+    /// Sets the maximum number of completed frames waiting for output, must be positive.
+    #[inline]
+    pub fn max_buffered_frames(mut self, max_buffered_frames: usize) -> Self {
+        if max_buffered_frames == 0 {
+            return self.fail(Error::ZeroBufferedFrames);
+        }
+
+        self.max_buffered_frames = Some(max_buffered_frames);
+        self
+    }
+
+    /// Sets the frame request mode.
+    #[inline]
+    pub fn mode(mut self, mode: RenderMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets whether to render the frames from the last one to the first one.
+    #[inline]
+    pub fn reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    /// Sets whether to write the YUV4MPEG2 stream and frame headers.
+    #[inline]
+    pub fn y4m(mut self, y4m: bool) -> Self {
+        self.y4m = y4m;
+        self
+    }
+
+    /// Sets whether to print the render progress to stderr.
+    #[inline]
+    pub fn progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sets the path of the timecodes v2 file to write.
+    #[inline]
+    pub fn timecodes<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.timecodes = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Evaluates the script and renders the selected output into `writer`.
+    ///
+    /// The alpha node of the output, if any, is written after the corresponding frames of the
+    /// main node.
+    pub fn run<W: Write + Send>(self, writer: W) -> Result<RenderStats, Error> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let env = Environment::from_file(&self.script, self.flags).map_err(Error::Script)?;
+
+        let index = self.output_index;
+        #[cfg(feature = "gte-vsscript-api-31")]
+        let (node, alpha_node) = env
+            .get_output(index)
+            .map_err(|source| Error::GetOutput { index, source })?;
+        #[cfg(not(feature = "gte-vsscript-api-31"))]
+        let (node, alpha_node) = (
+            env.get_output(index)
+                .map_err(|source| Error::GetOutput { index, source })?,
+            None,
+        );
+
+        let requests = match self.requests {
+            Some(requests) => requests,
+            None => env.get_core().map_err(Error::Script)?.num_threads(),
+        };
+
+        let timecodes = match self.timecodes {
+            Some(path) => {
+                let file =
+                    File::create(&path).map_err(|source| Error::CreateFile { path, source })?;
+                Some(Box::new(BufWriter::new(file)) as Box<dyn Write + Send>)
+            }
+            None => None,
+        };
+
+        let (start_frame, end_frame) = match self.range {
+            Some(range) => (range.start, Some(range.end - 1)),
+            None => (0, None),
+        };
+
+        let mut stderr_progress = StderrProgress::new();
+        let progress: Option<&mut (dyn ProgressReporter + Send)> = if self.progress {
+            Some(&mut stderr_progress)
+        } else {
+            None
+        };
+
+        let options = RenderOptions {
+            alpha_node,
+            start_frame,
+            end_frame,
+            requests,
+            max_buffered_frames: self.max_buffered_frames,
+            mode: self.mode,
+            order: if self.reverse {
+                FrameOrder::Reverse
+            } else {
+                FrameOrder::Forward
+            },
+            y4m: self.y4m,
+            progress,
+            timecodes,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+        };
+
+        render(&node, options, writer)
+    }
+}
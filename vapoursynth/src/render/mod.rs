@@ -0,0 +1,751 @@
+//! Rendering clips into raw or YUV4MPEG2 streams.
+//!
+//! This is the output pipeline of vspipe: frames are requested concurrently, reordered and
+//! written in order to a `Write` target.
+
+use std::cmp;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufWriter, Write};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::format::{ColorFamily, SampleType};
+use crate::frame::{Frame, FrameRef};
+use crate::node::{GetFrameError, Node};
+use crate::video_info::Property;
+
+mod errors;
+pub use self::errors::Error;
+
+mod progress;
+pub use self::progress::{ProgressReporter, StderrProgress};
+
+mod tee;
+pub use self::tee::Tee;
+
+mod y4m;
+pub use self::y4m::Y4mWriter;
+
+#[cfg(all(
+    feature = "vsscript-functions",
+    any(feature = "vapoursynth-functions", feature = "gte-vsscript-api-32")
+))]
+mod outputs;
+#[cfg(all(
+    feature = "vsscript-functions",
+    any(feature = "vapoursynth-functions", feature = "gte-vsscript-api-32")
+))]
+pub use self::outputs::render_outputs_to_files;
+
+#[cfg(all(
+    feature = "vsscript-functions",
+    any(feature = "vapoursynth-functions", feature = "gte-vsscript-api-32")
+))]
+mod job;
+#[cfg(all(
+    feature = "vsscript-functions",
+    any(feature = "vapoursynth-functions", feature = "gte-vsscript-api-32")
+))]
+pub use self::job::RenderJob;
+
+/// The default capacity of the output buffer in bytes.
+pub const DEFAULT_BUFFER_SIZE: usize = 1 << 20;
+
+/// How the frames are requested.
+///
+/// The sequential and fixed window modes don't depend on the number of threads of the machine,
+/// which makes them suitable for reproducible benchmarks.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum RenderMode {
+    /// Request as many frames as allowed by `requests` and `max_buffered_frames`.
+    Default,
+    /// Request frames strictly one at a time.
+    Sequential,
+    /// Keep `requests` frames in flight, without buffering more completed frames than that.
+    FixedWindow,
+}
+
+/// The order in which the frames are requested and written.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum FrameOrder {
+    /// From `start_frame` to `end_frame`.
+    Forward,
+    /// From `end_frame` to `start_frame`.
+    Reverse,
+    /// The listed frames, which may repeat. `start_frame` and `end_frame` are ignored.
+    Custom(Vec<usize>),
+}
+
+/// Options for `render()`.
+pub struct RenderOptions<'core> {
+    /// The alpha node, whose frames are written after the corresponding frames of the main node.
+    pub alpha_node: Option<Node<'core>>,
+
+    /// The first frame to output.
+    pub start_frame: usize,
+
+    /// The last frame to output, inclusive. `None` means the last frame of the clip.
+    pub end_frame: Option<usize>,
+
+    /// The number of concurrent frame requests, must be positive.
+    ///
+    /// Usually this is set to the number of threads of the core.
+    pub requests: usize,
+
+    /// The maximum number of completed frames waiting for output. No new frames are requested
+    /// while this many are buffered. `None` means twice the number of requests.
+    pub max_buffered_frames: Option<usize>,
+
+    /// The frame request mode.
+    pub mode: RenderMode,
+
+    /// The order of the frames.
+    pub order: FrameOrder,
+
+    /// Whether to write the YUV4MPEG2 stream and frame headers.
+    ///
+    /// The interlacing and the sample aspect ratio in the stream header are taken from the first
+    /// frame to be rendered.
+    pub y4m: bool,
+
+    /// The receiver of the render progress, if any. Use `StderrProgress` to print the progress
+    /// to stderr.
+    ///
+    /// The reporter stays borrowed until the render is finished, so its state can be inspected
+    /// afterwards.
+    pub progress: Option<&'core mut (dyn ProgressReporter + Send)>,
+
+    /// The target for the timecodes v2 file, if any.
+    pub timecodes: Option<Box<dyn Write + Send + 'core>>,
+
+    /// The capacity of the output buffer in bytes, 0 disables the buffering.
+    ///
+    /// The writer is wrapped in a `BufWriter`, so that large frames aren't written with many small
+    /// writes. The buffer is flushed by `Renderer::finish()`.
+    pub buffer_size: usize,
+}
+
+/// Statistics of a finished render.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RenderStats {
+    /// The number of frames written.
+    pub frames: usize,
+
+    /// The time it took to render the frames.
+    pub elapsed: Duration,
+
+    /// The frame request mode that was used.
+    pub mode: RenderMode,
+
+    /// The number of concurrent frame requests that was used.
+    pub requests: usize,
+}
+
+/// A render of the frames of a node, separating the rendering from the output finalization.
+///
+/// `render()` is a shorthand for `Renderer::new()`, `Renderer::render()` and
+/// `Renderer::finish()`.
+pub struct Renderer<'core, W: Write> {
+    shared_data: Arc<SharedData<'core, BufWriter<W>>>,
+    initial_requests: usize,
+    mode: RenderMode,
+    rendered: bool,
+}
+
+// A rational number for summing up the frame durations without accumulating rounding errors.
+#[derive(Debug, Clone, Copy)]
+struct Rational {
+    numerator: i64,
+    denominator: i64,
+}
+
+// The render parameters with the defaults resolved.
+struct Parameters<'core> {
+    node: Node<'core>,
+    alpha_node: Option<Node<'core>>,
+    // The frames to output, in order.
+    frames: Vec<usize>,
+    requests: usize,
+    max_buffered_frames: usize,
+    y4m: bool,
+}
+
+struct State<'core, W> {
+    writer: W,
+    timecodes: Option<Box<dyn Write + Send + 'core>>,
+    error: Option<Error>,
+    // Keyed by the position in `Parameters::frames`.
+    reorder_map: HashMap<usize, (Option<FrameRef<'core>>, Option<FrameRef<'core>>)>,
+    frames_requested: usize,
+    next_output_position: usize,
+    current_timecode: Rational,
+    callbacks_fired: usize,
+    callbacks_fired_alpha: usize,
+    progress: Option<&'core mut (dyn ProgressReporter + Send)>,
+}
+
+struct SharedData<'core, W> {
+    done_pair: (Mutex<bool>, Condvar),
+    parameters: Parameters<'core>,
+    state: Mutex<State<'core, W>>,
+}
+
+impl fmt::Display for RenderMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            RenderMode::Default => "default",
+            RenderMode::Sequential => "sequential",
+            RenderMode::FixedWindow => "fixed-window",
+        };
+        f.write_str(name)
+    }
+}
+
+impl<'core> Default for RenderOptions<'core> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            alpha_node: None,
+            start_frame: 0,
+            end_frame: None,
+            requests: 1,
+            max_buffered_frames: None,
+            mode: RenderMode::Default,
+            order: FrameOrder::Forward,
+            y4m: false,
+            progress: None,
+            timecodes: None,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+        }
+    }
+}
+
+impl RenderStats {
+    /// Returns the average number of frames written per second.
+    #[inline]
+    pub fn fps(&self) -> f64 {
+        self.frames as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+impl Rational {
+    #[inline]
+    fn new(numerator: i64, denominator: i64) -> Self {
+        fn gcd(mut a: i64, mut b: i64) -> i64 {
+            while b != 0 {
+                let t = a % b;
+                a = b;
+                b = t;
+            }
+            a.abs()
+        }
+
+        let gcd = gcd(numerator, denominator).max(1);
+        let sign = denominator.signum();
+        Self {
+            numerator: sign * numerator / gcd,
+            denominator: sign * denominator / gcd,
+        }
+    }
+
+    #[inline]
+    fn add(self, other: Self) -> Self {
+        Self::new(
+            self.numerator * other.denominator + other.numerator * self.denominator,
+            self.denominator * other.denominator,
+        )
+    }
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+}
+
+// Checks if the frame is completed, that is, we have the frame and, if needed, its alpha part.
+fn is_completed(entry: &(Option<FrameRef>, Option<FrameRef>), have_alpha: bool) -> bool {
+    entry.0.is_some() && (!have_alpha || entry.1.is_some())
+}
+
+// Returns the size of the frame data following each y4m FRAME header.
+fn y4m_frame_size(node: &Node) -> Option<usize> {
+    let info = node.info();
+    match (info.format, info.resolution) {
+        (Property::Constant(format), Property::Constant(resolution)) => {
+            Some(format.frame_size_bytes(resolution.width, resolution.height))
+        }
+        _ => None,
+    }
+}
+
+// YUV4MPEG2 streams store multi-byte samples in little-endian order.
+const Y4M_SWAP_BYTES: bool = cfg!(target_endian = "big");
+
+// Outputs the frame data, returning the number of bytes written.
+//
+// If `swap_bytes` is set, the byte order of multi-byte samples is reversed.
+pub(crate) fn write_frame_data<W: Write>(
+    writer: &mut W,
+    frame: &Frame,
+    swap_bytes: bool,
+) -> std::io::Result<usize> {
+    const RGB_REMAP: [usize; 3] = [1, 2, 0];
+
+    let mut written = 0;
+    let format = frame.format();
+    let bytes_per_sample = frame.bytes_per_sample();
+    let mut buf = Vec::new();
+    #[allow(clippy::needless_range_loop)]
+    for plane in 0..format.plane_count() {
+        let plane = if format.color_family() == ColorFamily::RGB {
+            RGB_REMAP[plane]
+        } else {
+            plane
+        };
+
+        if swap_bytes && bytes_per_sample > 1 {
+            for data in frame.rows(plane) {
+                buf.clear();
+                buf.extend(
+                    data.chunks_exact(bytes_per_sample)
+                        .flat_map(|sample| sample.iter().rev()),
+                );
+                writer.write_all(&buf)?;
+                written += buf.len();
+            }
+        } else if let Some(data) = frame.plane_contiguous(plane) {
+            writer.write_all(data)?;
+            written += data.len();
+        } else {
+            for data in frame.rows(plane) {
+                writer.write_all(data)?;
+                written += data.len();
+            }
+        }
+    }
+
+    Ok(written)
+}
+
+fn write_frames<W: Write>(
+    writer: &mut W,
+    parameters: &Parameters,
+    frame: &Frame,
+    alpha_frame: Option<&Frame>,
+) -> std::io::Result<()> {
+    if parameters.y4m {
+        writeln!(writer, "FRAME")?;
+    }
+
+    let written = write_frame_data(writer, frame, parameters.y4m && Y4M_SWAP_BYTES)?;
+    if parameters.y4m {
+        debug_assert_eq!(
+            Some(written),
+            y4m_frame_size(&parameters.node),
+            "The frame size doesn't match the y4m header"
+        );
+    }
+
+    if let Some(alpha_frame) = alpha_frame {
+        write_frame_data(writer, alpha_frame, false)?;
+    }
+
+    Ok(())
+}
+
+// Returns the timecode of the frame in milliseconds.
+//
+// The precedence order is: the frame's _AbsoluteTime, then the sum of the previous frames'
+// durations, which in turn fall back to the clip frame rate (see `frame_duration()`).
+fn frame_timecode<W>(frame: &Frame, state: &State<W>) -> f64 {
+    match frame.absolute_time() {
+        Some(seconds) => seconds * 1000f64,
+        None => state.current_timecode.to_f64() * 1000f64,
+    }
+}
+
+// Returns the frame duration, taken from the frame's _DurationNum and _DurationDen, or from the
+// clip frame rate if those are missing.
+fn frame_duration(frame: &Frame, n: usize, parameters: &Parameters) -> Result<Rational, Error> {
+    let props = frame.props();
+    match (props.get_int("_DurationNum"), props.get_int("_DurationDen")) {
+        (Ok(duration_num), Ok(duration_den)) => {
+            if duration_den == 0 {
+                return Err(Error::ZeroDurationDenominator { frame: n });
+            }
+
+            Ok(Rational::new(duration_num, duration_den))
+        }
+        _ => match parameters.node.info().framerate {
+            Property::Constant(framerate) => Ok(Rational::new(
+                framerate.denominator as i64,
+                framerate.numerator as i64,
+            )),
+            Property::Variable => Err(Error::VariableFramerate),
+        },
+    }
+}
+
+// Writes the frame timecode and advances the current timecode.
+fn write_timecode<W>(
+    frame: &Frame,
+    n: usize,
+    state: &mut State<W>,
+    parameters: &Parameters,
+) -> Result<(), Error> {
+    let timecode = frame_timecode(frame, state);
+    if let Some(ref mut timecodes) = state.timecodes {
+        writeln!(timecodes, "{:.6}", timecode)
+            .map_err(|source| Error::Timecode { frame: n, source })?;
+    }
+
+    let duration = frame_duration(frame, n, parameters)?;
+    state.current_timecode = state.current_timecode.add(duration);
+
+    Ok(())
+}
+
+// Returns the number of requested frames which haven't been fully retrieved yet.
+fn frames_in_flight<W>(state: &State<W>) -> usize {
+    state.frames_requested - cmp::min(state.callbacks_fired, state.callbacks_fired_alpha)
+}
+
+// Returns the number of completed frames waiting in the reorder map.
+fn buffered_frames<W>(state: &State<W>, parameters: &Parameters) -> usize {
+    state
+        .reorder_map
+        .values()
+        .filter(|entry| is_completed(entry, parameters.alpha_node.is_some()))
+        .count()
+}
+
+// Requests the frame at `position` in the frame order and, if needed, its alpha part.
+fn request_frame<'core, W: Write + Send + 'core>(
+    shared_data: &Arc<SharedData<'core, W>>,
+    position: usize,
+) {
+    let parameters = &shared_data.parameters;
+    let n = parameters.frames[position];
+
+    let shared_data_2 = shared_data.clone();
+    parameters.node.get_frame_async(n, move |frame, _, _| {
+        frame_done_callback(frame, position, &shared_data_2, false)
+    });
+
+    if let Some(ref alpha_node) = parameters.alpha_node {
+        let shared_data_2 = shared_data.clone();
+        alpha_node.get_frame_async(n, move |frame, _, _| {
+            frame_done_callback(frame, position, &shared_data_2, true)
+        });
+    }
+}
+
+fn frame_done_callback<'core, W: Write + Send + 'core>(
+    frame: Result<FrameRef<'core>, GetFrameError>,
+    position: usize,
+    shared_data: &Arc<SharedData<'core, W>>,
+    alpha: bool,
+) {
+    let parameters = &shared_data.parameters;
+    let mut state = shared_data.state.lock().unwrap();
+    let state = &mut *state;
+
+    // Increase the progress counter.
+    if !alpha {
+        state.callbacks_fired += 1;
+        if parameters.alpha_node.is_none() {
+            state.callbacks_fired_alpha += 1;
+        }
+    } else {
+        state.callbacks_fired_alpha += 1;
+    }
+
+    match frame {
+        Err(error) => {
+            if state.error.is_none() {
+                state.error = Some(Error::GetFrame(error.into()));
+            }
+        }
+        Ok(frame) => {
+            // Store the frame in the reorder map.
+            {
+                let entry = state.reorder_map.entry(position).or_insert((None, None));
+                if alpha {
+                    entry.1 = Some(frame);
+                } else {
+                    entry.0 = Some(frame);
+                }
+            }
+
+            // Output all completed frames.
+            while state
+                .reorder_map
+                .get(&state.next_output_position)
+                .map(|entry| is_completed(entry, parameters.alpha_node.is_some()))
+                .unwrap_or(false)
+            {
+                let next_output_position = state.next_output_position;
+                let (frame, alpha_frame) = state.reorder_map.remove(&next_output_position).unwrap();
+                let n = parameters.frames[next_output_position];
+
+                let frame = frame.unwrap();
+                if state.error.is_none() {
+                    if let Err(source) = write_frames(
+                        &mut state.writer,
+                        parameters,
+                        &frame,
+                        alpha_frame.as_deref(),
+                    ) {
+                        state.error = Some(Error::Output { frame: n, source });
+                    }
+                }
+
+                if state.timecodes.is_some() && state.error.is_none() {
+                    if let Err(error) = write_timecode(&frame, n, state, parameters) {
+                        state.error = Some(error);
+                    }
+                }
+
+                state.next_output_position += 1;
+            }
+
+            // Request more frames, unless too many completed frames are waiting for output
+            // because an earlier frame is taking a long time.
+            while state.frames_requested < parameters.frames.len()
+                && state.error.is_none()
+                && frames_in_flight(state) < parameters.requests
+                && buffered_frames(state, parameters) < parameters.max_buffered_frames
+            {
+                request_frame(shared_data, state.frames_requested);
+                state.frames_requested += 1;
+            }
+        }
+    }
+
+    // Report the progress.
+    if let Some(ref mut progress) = state.progress {
+        progress.on_frame(state.callbacks_fired);
+    }
+
+    // This condition works with error handling, unlike checking the next output frame.
+    if state.callbacks_fired == state.frames_requested
+        && state.callbacks_fired_alpha == state.frames_requested
+    {
+        *shared_data.done_pair.0.lock().unwrap() = true;
+        shared_data.done_pair.1.notify_one();
+    }
+}
+
+/// Renders the frames of `node` into `writer`, blocking until all frames are written.
+///
+/// The frames are written in the order specified by `options.order`, one plane after another with
+/// the row padding removed. For RGB formats the planes are written in the G, B, R order. If
+/// `options.y4m` is set, the YUV4MPEG2 stream header and frame headers are written as well, and
+/// multi-byte samples are written in little-endian order. Otherwise the samples are written in
+/// the native byte order.
+///
+/// `writer` is buffered according to `options.buffer_size` and flushed after all frames are
+/// written. Use `Renderer` to control when the output is finalized.
+pub fn render<'core, W>(
+    node: &Node<'core>,
+    options: RenderOptions<'core>,
+    writer: W,
+) -> Result<RenderStats, Error>
+where
+    W: Write + Send + 'core,
+{
+    let mut renderer = Renderer::new(node, options, writer)?;
+    let stats = renderer.render()?;
+    renderer.finish()?;
+    Ok(stats)
+}
+
+impl<'core, W> Renderer<'core, W>
+where
+    W: Write + Send + 'core,
+{
+    /// Creates a renderer for the frames of `node`.
+    ///
+    /// The options are validated and the y4m and timecodes headers are written, if needed. The
+    /// interlacing and the sample aspect ratio in the y4m header are taken from the properties of
+    /// the first frame to be rendered, which is fetched for this.
+    pub fn new(
+        node: &Node<'core>,
+        options: RenderOptions<'core>,
+        writer: W,
+    ) -> Result<Self, Error> {
+        let info = node.info();
+        if let Property::Variable = info.format {
+            return Err(Error::VariableFormat);
+        }
+        if let Property::Variable = info.resolution {
+            return Err(Error::VariableResolution);
+        }
+        if let Property::Variable = info.framerate {
+            return Err(Error::VariableFramerate);
+        }
+
+        let num_frames = info.frame_count().ok_or(Error::UnknownLength)?;
+
+        let frames = match options.order {
+            FrameOrder::Forward | FrameOrder::Reverse => {
+                let start_frame = options.start_frame;
+                let end_frame = options.end_frame.unwrap_or(num_frames - 1);
+                if end_frame < start_frame || end_frame >= num_frames {
+                    return Err(Error::InvalidRange {
+                        start: start_frame,
+                        end: end_frame,
+                        num_frames,
+                    });
+                }
+
+                if options.order == FrameOrder::Forward {
+                    (start_frame..=end_frame).collect()
+                } else {
+                    (start_frame..=end_frame).rev().collect()
+                }
+            }
+            FrameOrder::Custom(frames) => {
+                if let Some(&frame) = frames.iter().find(|&&frame| frame >= num_frames) {
+                    return Err(Error::InvalidFrame { frame, num_frames });
+                }
+
+                frames
+            }
+        };
+
+        let (requests, max_buffered_frames) = match options.mode {
+            RenderMode::Default => (
+                options.requests,
+                options.max_buffered_frames.unwrap_or(options.requests * 2),
+            ),
+            RenderMode::Sequential => (1, 1),
+            RenderMode::FixedWindow => (options.requests, options.requests),
+        };
+        if requests == 0 {
+            return Err(Error::ZeroRequests);
+        }
+        if max_buffered_frames == 0 {
+            return Err(Error::ZeroBufferedFrames);
+        }
+
+        let mut writer = BufWriter::with_capacity(options.buffer_size, writer);
+
+        // Write the y4m header.
+        if options.y4m {
+            if options.alpha_node.is_some() {
+                return Err(Error::Y4mWithAlpha);
+            }
+
+            y4m::write_header(&mut writer, node, frames.first().copied())?;
+        }
+
+        // Write the timecodes header.
+        let mut timecodes = options.timecodes;
+        if let Some(ref mut timecodes) = timecodes {
+            writeln!(timecodes, "# timecode format v2").map_err(Error::Header)?;
+        }
+
+        let initial_requests = cmp::min(requests, frames.len());
+
+        let shared_data = Arc::new(SharedData {
+            done_pair: (Mutex::new(false), Condvar::new()),
+            parameters: Parameters {
+                node: node.clone(),
+                alpha_node: options.alpha_node,
+                frames,
+                requests,
+                max_buffered_frames,
+                y4m: options.y4m,
+            },
+            state: Mutex::new(State {
+                writer,
+                timecodes,
+                error: None,
+                reorder_map: HashMap::new(),
+                frames_requested: initial_requests,
+                next_output_position: 0,
+                current_timecode: Rational::new(0, 1),
+                callbacks_fired: 0,
+                callbacks_fired_alpha: 0,
+                progress: options.progress,
+            }),
+        });
+
+        Ok(Self {
+            shared_data,
+            initial_requests,
+            mode: options.mode,
+            rendered: false,
+        })
+    }
+
+    /// Renders the frames, blocking until all frames are written.
+    ///
+    /// The output is not flushed, call `finish()` afterwards. Returns an error if the frames have
+    /// already been rendered.
+    pub fn render(&mut self) -> Result<RenderStats, Error> {
+        if self.rendered {
+            return Err(Error::AlreadyRendered);
+        }
+        self.rendered = true;
+
+        let shared_data = &self.shared_data;
+
+        if let Some(ref mut progress) = shared_data.state.lock().unwrap().progress {
+            progress.on_start(shared_data.parameters.frames.len());
+        }
+
+        // Record the start time.
+        let start_time = Instant::now();
+
+        // Start off by requesting some frames.
+        for position in 0..self.initial_requests {
+            request_frame(shared_data, position);
+        }
+
+        // There's nothing to wait for if the custom frame order is empty.
+        let (ref lock, ref cvar) = shared_data.done_pair;
+        let mut done = lock.lock().unwrap();
+        while !*done && self.initial_requests > 0 {
+            done = cvar.wait(done).unwrap();
+        }
+
+        let elapsed = start_time.elapsed();
+        drop(done);
+
+        let mut state = shared_data.state.lock().unwrap();
+        if let Some(ref mut progress) = state.progress {
+            progress.on_finish();
+        }
+        if let Some(error) = state.error.take() {
+            return Err(error);
+        }
+
+        Ok(RenderStats {
+            frames: state.next_output_position,
+            elapsed,
+            mode: self.mode,
+            requests: shared_data.parameters.requests,
+        })
+    }
+
+    /// Finalizes the output by flushing the writer and the timecodes target.
+    ///
+    /// Returns the error that occurred during rendering, if it hasn't been returned by `render()`
+    /// already. Since the renderer is consumed, the output can't be finalized twice.
+    pub fn finish(self) -> Result<(), Error> {
+        let mut state = self.shared_data.state.lock().unwrap();
+        if let Some(error) = state.error.take() {
+            return Err(error);
+        }
+
+        state.writer.flush().map_err(Error::Flush)?;
+        if let Some(ref mut timecodes) = state.timecodes {
+            timecodes.flush().map_err(Error::Flush)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use crate::render::{render, Error, RenderOptions, RenderStats};
+use crate::vsscript::Environment;
+
+/// Renders several outputs of a script environment, each to its own file.
+///
+/// The script is evaluated once and all outputs share the same core, so the work common to the
+/// outputs (like decoding the source) isn't repeated across separate environments. The outputs are
+/// rendered one after another in the order of `mapping`, with the options taken from `options`.
+/// The `alpha_node`, `timecodes` and `progress` fields of `options` are ignored: the alpha node of
+/// each output is used instead, and no timecodes or progress are reported.
+///
+/// All listed outputs are attempted even if some of them fail; the failures are then reported
+/// together in an `Error::Outputs`. On success, the statistics are returned in the order of
+/// `mapping`.
+pub fn render_outputs_to_files(
+    env: &Environment,
+    mapping: &[(i32, PathBuf)],
+    options: &RenderOptions,
+) -> Result<Vec<RenderStats>, Error> {
+    let mut stats = Vec::with_capacity(mapping.len());
+    let mut errors = Vec::new();
+
+    for (index, path) in mapping {
+        match render_output(env, *index, path, options) {
+            Ok(output_stats) => stats.push(output_stats),
+            Err(error) => errors.push((*index, error)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(stats)
+    } else {
+        Err(Error::Outputs(errors))
+    }
+}
+
+// Renders a single output of the environment into the file at `path`.
+fn render_output(
+    env: &Environment,
+    index: i32,
+    path: &PathBuf,
+    options: &RenderOptions,
+) -> Result<RenderStats, Error> {
+    #[cfg(feature = "gte-vsscript-api-31")]
+    let (node, alpha_node) = env
+        .get_output(index)
+        .map_err(|source| Error::GetOutput { index, source })?;
+    #[cfg(not(feature = "gte-vsscript-api-31"))]
+    let (node, alpha_node) = (
+        env.get_output(index)
+            .map_err(|source| Error::GetOutput { index, source })?,
+        None,
+    );
+
+    let file = File::create(path).map_err(|source| Error::CreateFile {
+        path: path.clone(),
+        source,
+    })?;
+
+    let options = RenderOptions {
+        alpha_node,
+        start_frame: options.start_frame,
+        end_frame: options.end_frame,
+        requests: options.requests,
+        max_buffered_frames: options.max_buffered_frames,
+        mode: options.mode,
+        order: options.order.clone(),
+        y4m: options.y4m,
+        progress: None,
+        timecodes: None,
+        buffer_size: options.buffer_size,
+    };
+
+    render(&node, options, file)
+}
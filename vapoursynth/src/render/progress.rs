@@ -0,0 +1,82 @@
+use std::time::Instant;
+
+/// A trait for receiving the progress of a render.
+///
+/// The methods are called with the render state locked, from whichever thread delivered the frame,
+/// so they should return quickly.
+pub trait ProgressReporter {
+    /// Called before any frames are requested, with the total number of frames to render.
+    #[inline]
+    fn on_start(&mut self, _total: usize) {}
+
+    /// Called every time a frame is retrieved, with the number of frames retrieved so far.
+    ///
+    /// The frames are counted as they arrive, which may happen before they are written.
+    fn on_frame(&mut self, done: usize);
+
+    /// Called once all requested frames have been retrieved or the render has failed.
+    #[inline]
+    fn on_finish(&mut self) {}
+}
+
+/// A progress reporter printing the frame count and speed to stderr, like vspipe does.
+///
+/// The progress is printed on a single line which is overwritten on every update. The speed is
+/// recomputed every 10 seconds.
+#[derive(Debug, Clone, Copy)]
+pub struct StderrProgress {
+    total: usize,
+    last_fps_report_time: Instant,
+    last_fps_report_frames: usize,
+    fps: Option<f64>,
+}
+
+impl StderrProgress {
+    /// Creates a new stderr progress reporter.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            total: 0,
+            last_fps_report_time: Instant::now(),
+            last_fps_report_frames: 0,
+            fps: None,
+        }
+    }
+}
+
+impl Default for StderrProgress {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressReporter for StderrProgress {
+    #[inline]
+    fn on_start(&mut self, total: usize) {
+        *self = Self {
+            total,
+            ..Self::new()
+        };
+    }
+
+    fn on_frame(&mut self, done: usize) {
+        // Figure out the FPS.
+        let current = Instant::now();
+        let elapsed = current.duration_since(self.last_fps_report_time);
+
+        if elapsed.as_secs() > 10 {
+            self.fps = Some((done - self.last_fps_report_frames) as f64 / elapsed.as_secs_f64());
+            self.last_fps_report_time = current;
+            self.last_fps_report_frames = done;
+        }
+
+        eprint!("Frame: {}/{}", done, self.total);
+
+        if let Some(fps) = self.fps {
+            eprint!(" ({:.2} fps)", fps);
+        }
+
+        eprint!("\r");
+    }
+}
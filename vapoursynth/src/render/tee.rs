@@ -0,0 +1,70 @@
+use std::io::{self, Write};
+
+/// A writer that duplicates the written bytes to several writers.
+///
+/// This allows, for example, saving the rendered output and computing its checksum in one pass.
+/// A failing writer doesn't stop the other writers from receiving the data: the errors of all
+/// writers are combined into one.
+#[derive(Debug)]
+pub struct Tee<W: Write> {
+    writers: Vec<W>,
+}
+
+impl<W: Write> Tee<W> {
+    /// Creates a writer duplicating the bytes to `writers`.
+    #[inline]
+    pub fn new(writers: Vec<W>) -> Self {
+        Self { writers }
+    }
+
+    /// Returns a reference to the underlying writers.
+    #[inline]
+    pub fn get_ref(&self) -> &[W] {
+        &self.writers
+    }
+
+    /// Returns a mutable reference to the underlying writers.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut [W] {
+        &mut self.writers
+    }
+
+    /// Returns the underlying writers, consuming the `Tee`.
+    #[inline]
+    pub fn into_inner(self) -> Vec<W> {
+        self.writers
+    }
+
+    // Calls `f` on every writer, combining the errors. A single error is returned as is.
+    fn for_each<F>(&mut self, mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&mut W) -> io::Result<()>,
+    {
+        let mut errors = self
+            .writers
+            .iter_mut()
+            .filter_map(|writer| f(writer).err())
+            .collect::<Vec<_>>();
+
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.pop().unwrap()),
+            _ => {
+                let messages = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+                Err(io::Error::other(messages.join("; ")))
+            }
+        }
+    }
+}
+
+impl<W: Write> Write for Tee<W> {
+    /// Writes the whole buffer to every writer.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.for_each(|writer| writer.write_all(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.for_each(Write::flush)
+    }
+}
@@ -0,0 +1,177 @@
+use std::io::Write;
+
+use crate::format::{ColorFamily, SampleType};
+use crate::frame::Frame;
+use crate::node::Node;
+use crate::render::{write_frame_data, Error, Y4M_SWAP_BYTES};
+use crate::video_info::Property;
+
+/// A writer for YUV4MPEG2 streams.
+///
+/// The stream header is written on creation, then each `write_frame()` call writes one frame.
+/// Multi-byte samples are written in little-endian order regardless of the host byte order, as
+/// YUV4MPEG2 consumers expect.
+#[derive(Debug)]
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+    // The size of the frame data following each FRAME header.
+    frame_size: usize,
+    frames_written: usize,
+}
+
+// Writes the YUV4MPEG2 stream header, returning the frame data size.
+//
+// The interlacing and the sample aspect ratio are taken from the `_FieldBased`, `_SARNum` and
+// `_SARDen` properties of `first_frame`, the first frame to be written. Without frames to write,
+// the stream is marked as progressive with an unknown sample aspect ratio.
+pub(super) fn write_header<W: Write>(
+    writer: &mut W,
+    node: &Node,
+    first_frame: Option<usize>,
+) -> Result<usize, Error> {
+    let info = node.info();
+
+    let format = match info.format {
+        Property::Constant(format) => format,
+        Property::Variable => return Err(Error::VariableFormat),
+    };
+    let resolution = match info.resolution {
+        Property::Constant(resolution) => resolution,
+        Property::Variable => return Err(Error::VariableResolution),
+    };
+    let framerate = match info.framerate {
+        Property::Constant(framerate) => framerate,
+        Property::Variable => return Err(Error::VariableFramerate),
+    };
+
+    let num_frames = info.frame_count().ok_or(Error::UnknownLength)?;
+
+    let mut colorspace = String::new();
+    match format.color_family() {
+        ColorFamily::Gray => {
+            colorspace += "mono";
+            if format.bits_per_sample() > 8 {
+                colorspace += &format.bits_per_sample().to_string();
+            }
+        }
+        ColorFamily::YUV => {
+            let subsampling = format
+                .subsampling()
+                .map_err(|error| Error::Y4mUnsupportedFormat(Some(error)))?;
+            colorspace += subsampling.y4m_tag();
+
+            if format.sample_type() == SampleType::Float {
+                colorspace += match format.bits_per_sample() {
+                    16 => "ph",
+                    32 => "ps",
+                    64 => "pd",
+                    _ => unreachable!(),
+                };
+            } else if format.bits_per_sample() > 8 {
+                colorspace += &format!("p{}", format.bits_per_sample());
+            }
+        }
+        _ => return Err(Error::Y4mUnsupportedFormat(None)),
+    }
+
+    let (interlacing, sar_num, sar_den) = match first_frame {
+        Some(n) => {
+            let frame = node
+                .get_frame(n)
+                .map_err(|error| Error::GetFrame(error.into()))?;
+            let props = frame.props();
+
+            let interlacing = match props.get_int("_FieldBased") {
+                Ok(1) => 'b',
+                Ok(2) => 't',
+                _ => 'p',
+            };
+
+            match (props.get_int("_SARNum"), props.get_int("_SARDen")) {
+                (Ok(sar_num), Ok(sar_den)) if sar_num > 0 && sar_den > 0 => {
+                    (interlacing, sar_num, sar_den)
+                }
+                _ => (interlacing, 0, 0),
+            }
+        }
+        None => ('p', 0, 0),
+    };
+
+    writeln!(
+        writer,
+        "YUV4MPEG2 C{} W{} H{} F{}:{} I{} A{}:{} XLENGTH={}",
+        colorspace,
+        resolution.width,
+        resolution.height,
+        framerate.numerator,
+        framerate.denominator,
+        interlacing,
+        sar_num,
+        sar_den,
+        num_frames
+    )
+    .map_err(Error::Header)?;
+
+    Ok(format.frame_size_bytes(resolution.width, resolution.height))
+}
+
+impl<W: Write> Y4mWriter<W> {
+    /// Creates a writer for the frames of `node` and writes the stream header.
+    ///
+    /// The header describes the colorspace, resolution, framerate and length of the clip, as well
+    /// as the interlacing and the sample aspect ratio, taken from the `_FieldBased`, `_SARNum` and
+    /// `_SARDen` properties of frame 0.
+    ///
+    /// Returns an error if the clip can't be represented in a YUV4MPEG2 stream.
+    pub fn from_node(mut writer: W, node: &Node) -> Result<Self, Error> {
+        let frame_size = write_header(&mut writer, node, Some(0))?;
+
+        Ok(Self {
+            writer,
+            frame_size,
+            frames_written: 0,
+        })
+    }
+
+    /// Writes a frame header followed by the frame data.
+    ///
+    /// Returns an error if the frame data size doesn't match the stream header.
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<(), Error> {
+        let n = self.frames_written;
+        let format = frame.format();
+        if format.frame_size_bytes(frame.width(0), frame.height(0)) != self.frame_size {
+            return Err(Error::Y4mFrameMismatch { frame: n });
+        }
+
+        writeln!(self.writer, "FRAME")
+            .and_then(|_| write_frame_data(&mut self.writer, frame, Y4M_SWAP_BYTES))
+            .map_err(|source| Error::Output { frame: n, source })?;
+
+        self.frames_written += 1;
+        Ok(())
+    }
+
+    /// Returns the number of frames written so far.
+    #[inline]
+    pub fn frames_written(&self) -> usize {
+        self.frames_written
+    }
+
+    /// Returns a reference to the underlying writer.
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Returns the underlying writer, consuming the `Y4mWriter`.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
@@ -9,9 +9,11 @@ use super::*;
 mod need_api_and_vsscript {
     use std::ffi::CStr;
     use std::fmt::Debug;
+    use std::io;
     use std::mem;
     use std::slice;
     use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
 
     use super::*;
     use function::Function;
@@ -105,10 +107,30 @@ mod need_api_and_vsscript {
         #[cfg(not(feature = "gte-vapoursynth-api-32"))]
         assert_eq!(info.num_frames, Property::Constant(100));
 
+        assert_eq!(info.to_string(), "RGB24, 1920x1080, 60/1 fps, 100 frames");
+        assert_eq!(
+            node.get_info_string(None),
+            "Width: 1920\n\
+             Height: 1080\n\
+             Frames: 100\n\
+             FPS: 60/1 (60.000 fps)\n\
+             Format Name: RGB24\n\
+             Color Family: RGB\n\
+             Alpha: No\n\
+             Sample Type: Integer\n\
+             Bits: 8\n\
+             SubSampling W: 0\n\
+             SubSampling H: 0\n"
+        );
+
         let frame = node.get_frame(0).unwrap();
         green_frame_test(&frame);
         props_test(&frame, 60);
         env_video_var_test(env);
+
+        let frame = node.get_frame_with_errbuf(99, 1).unwrap();
+        green_frame_test(&frame);
+        assert!(node.get_frame_with_errbuf(100, 1).is_err());
     }
 
     #[test]
@@ -218,7 +240,7 @@ mod need_api_and_vsscript {
             vsscript::Environment::from_file("test-vpy/alpha.vpy", vsscript::EvalFlags::Nothing)
                 .unwrap();
 
-        let (_, alpha_node) = {
+        let (node, alpha_node) = {
             let output = env.get_output(0);
             assert!(output.is_ok());
             output.unwrap()
@@ -226,6 +248,9 @@ mod need_api_and_vsscript {
 
         assert!(alpha_node.is_some());
         let alpha_node = alpha_node.unwrap();
+        assert!(node
+            .get_info_string(Some(&alpha_node))
+            .contains("\nAlpha: Yes\n"));
 
         let info = alpha_node.info();
 
@@ -412,6 +437,64 @@ mod need_api_and_vsscript {
             .unwrap_or(false));
     }
 
+    #[test]
+    fn set_output() {
+        let env =
+            vsscript::Environment::from_script(include_str!("../test-vpy/green.vpy")).unwrap();
+
+        #[cfg(feature = "gte-vsscript-api-31")]
+        let node = env.get_output(0).unwrap().0;
+        #[cfg(not(feature = "gte-vsscript-api-31"))]
+        let node = env.get_output(0).unwrap();
+
+        assert!(env.set_output(3, &node, None).is_ok());
+
+        #[cfg(feature = "gte-vsscript-api-31")]
+        {
+            let (output, alpha_node) = env.get_output(3).unwrap();
+            assert_eq!(output.info().format, node.info().format);
+            assert!(alpha_node.is_none());
+
+            assert!(env.set_output(4, &node, Some(&node)).is_ok());
+            assert!(env.get_output(4).unwrap().1.is_some());
+        }
+        #[cfg(not(feature = "gte-vsscript-api-31"))]
+        assert!(env.get_output(3).is_ok());
+
+        // The temporary variables are cleaned up.
+        let mut map = OwnedMap::new(API::get().unwrap());
+        assert!(env
+            .get_variable("__vapoursynth_rs_output_node", &mut map)
+            .is_err());
+
+        assert!(env.clear_output(3).is_ok());
+    }
+
+    #[test]
+    fn outputs_info() {
+        let env = vsscript::Environment::new().unwrap();
+        assert_eq!(env.output_indices().unwrap(), Vec::<i32>::new());
+        assert!(env.outputs_info().unwrap().is_empty());
+
+        let env =
+            vsscript::Environment::from_script(include_str!("../test-vpy/green.vpy")).unwrap();
+
+        #[cfg(feature = "gte-vsscript-api-31")]
+        let node = env.get_output(0).unwrap().0;
+        #[cfg(not(feature = "gte-vsscript-api-31"))]
+        let node = env.get_output(0).unwrap();
+
+        assert!(env.set_output(5, &node, None).is_ok());
+        assert_eq!(env.output_indices().unwrap(), vec![0, 5]);
+
+        let outputs = env.outputs_info().unwrap();
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].index, 0);
+        assert_eq!(outputs[1].index, 5);
+        assert_eq!(outputs[0].info.format, node.info().format);
+        assert!(!outputs[0].has_alpha);
+    }
+
     #[test]
     fn iterators() {
         let env =
@@ -496,6 +579,566 @@ mod need_api_and_vsscript {
         }
     }
 
+    #[test]
+    fn get_frame_async_cancellable() {
+        let env =
+            vsscript::Environment::from_file("test-vpy/green.vpy", vsscript::EvalFlags::Nothing)
+                .unwrap();
+
+        #[cfg(feature = "gte-vsscript-api-31")]
+        let node = env.get_output(0).unwrap().0;
+        #[cfg(not(feature = "gte-vsscript-api-31"))]
+        let node = env.get_output(0).unwrap();
+
+        let (tx, rx) = channel();
+        let handle = node.get_frame_async_cancellable(0, move |frame, n, _| {
+            assert!(frame.is_ok());
+            assert_eq!(n, 0);
+            assert_eq!(tx.send(()), Ok(()));
+        });
+        assert!(!handle.is_cancelled());
+        assert_eq!(rx.recv(), Ok(()));
+
+        handle.cancel();
+        assert!(handle.is_cancelled());
+
+        // Whether the callback runs depends on timing, but the sender is dropped either way.
+        let (tx, rx) = channel();
+        drop(node.get_frame_async_cancellable(1, move |_, _, _| {
+            let _ = tx.send(());
+        }));
+        let _ = rx.recv();
+    }
+
+    #[test]
+    fn stream() {
+        let env =
+            vsscript::Environment::from_file("test-vpy/green.vpy", vsscript::EvalFlags::Nothing)
+                .unwrap();
+
+        #[cfg(feature = "gte-vsscript-api-31")]
+        let node = env.get_output(0).unwrap().0;
+        #[cfg(not(feature = "gte-vsscript-api-31"))]
+        let node = env.get_output(0).unwrap();
+
+        let mut frames = node
+            .stream(10..20, 3)
+            .map(|item| {
+                let (n, frame) = item.unwrap();
+                green_frame_test(&frame);
+                n
+            })
+            .collect::<Vec<_>>();
+        frames.sort_unstable();
+        assert_eq!(frames, (10..20).collect::<Vec<_>>());
+
+        // Dropping the stream early stops the requests.
+        let mut stream = node.stream(0..100, 2);
+        assert_eq!(stream.len(), 100);
+        assert!(stream.recv().unwrap().is_ok());
+        assert_eq!(stream.len(), 99);
+        drop(stream);
+
+        // The clip only has 100 frames.
+        let stream = node.stream(99..101, 2);
+        let mut results = stream.collect::<Vec<_>>();
+        results.sort_unstable_by_key(|item| match item {
+            Ok((n, _)) => *n,
+            Err(error) => error.frame(),
+        });
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert_eq!(results[1].as_ref().unwrap_err().frame(), 100);
+    }
+
+    #[test]
+    fn eval_flags() {
+        use vsscript::EvalFlags;
+
+        assert_eq!(EvalFlags::Nothing, EvalFlags::empty());
+        assert_eq!(EvalFlags::default(), EvalFlags::empty());
+        assert_eq!(EvalFlags::SetWorkingDir, EvalFlags::SET_WORKING_DIR);
+        assert_eq!(
+            EvalFlags::empty() | EvalFlags::SET_WORKING_DIR,
+            EvalFlags::SetWorkingDir
+        );
+
+        for flags in [EvalFlags::empty(), EvalFlags::SET_WORKING_DIR] {
+            let env = vsscript::Environment::from_file("test-vpy/green.vpy", flags).unwrap();
+
+            #[cfg(feature = "gte-vsscript-api-31")]
+            let node = env.get_output(0).unwrap().0;
+            #[cfg(not(feature = "gte-vsscript-api-31"))]
+            let node = env.get_output(0).unwrap();
+
+            green_frame_test(&node.get_frame(0).unwrap());
+        }
+    }
+
+    #[test]
+    fn eval_to_clip() {
+        let mut env = vsscript::Environment::new().unwrap();
+        let clip = env
+            .eval_to_clip("test-vpy/green.vpy", vsscript::EvalFlags::Nothing)
+            .unwrap();
+
+        let api = API::get().unwrap();
+        let mut args = OwnedMap::new(api);
+        args.set_int("first", 10).unwrap();
+        args.set_int("last", 19).unwrap();
+        let clip = clip.invoke("std", "Trim", args).unwrap();
+
+        assert_eq!(clip.num_frames(), Some(10));
+        assert_eq!(clip.node().info().frame_count(), Some(10));
+        green_frame_test(&clip.node().get_frame(0).unwrap());
+
+        let error = clip
+            .invoke("std", "NoSuchFilter", OwnedMap::new(api))
+            .unwrap_err();
+        assert!(matches!(error, clip::Error::Invoke(_)));
+        let error = clip
+            .invoke("no_such_namespace", "Trim", OwnedMap::new(api))
+            .unwrap_err();
+        assert!(matches!(error, clip::Error::NoSuchPlugin(_)));
+    }
+
+    #[test]
+    fn output_clip() {
+        let env =
+            vsscript::Environment::from_file("test-vpy/green.vpy", vsscript::EvalFlags::Nothing)
+                .unwrap();
+
+        let clip = env.output_clip(0).unwrap();
+        assert_eq!(clip.num_frames(), Some(100));
+        green_frame_test(&clip.node().get_frame(0).unwrap());
+
+        // The environment is still usable while the clip is alive.
+        assert_eq!(env.output_indices().unwrap(), vec![0]);
+        assert!(matches!(env.output_clip(1), Err(vsscript::Error::NoOutput)));
+    }
+
+    #[test]
+    fn function_from_closure() {
+        let mut env = vsscript::Environment::new().unwrap();
+        let clip = env
+            .eval_to_clip("test-vpy/green.vpy", vsscript::EvalFlags::Nothing)
+            .unwrap();
+
+        let api = API::get().unwrap();
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let eval = {
+            let requested = requested.clone();
+            let node = clip.node().clone();
+            Function::from_closure(api, clip.core(), move |in_| {
+                requested.lock().unwrap().push(in_.get_int("n").unwrap());
+
+                let mut out = OwnedMap::new(api);
+                out.set_node("val", &node).unwrap();
+                out
+            })
+        };
+
+        let mut args = OwnedMap::new(api);
+        args.set_function("eval", &eval).unwrap();
+        let clip = clip.invoke("std", "FrameEval", args).unwrap();
+
+        green_frame_test(&clip.node().get_frame(5).unwrap());
+        assert_eq!(*requested.lock().unwrap(), [5]);
+
+        // Errors set on the returned map are passed to the caller.
+        let failing = Function::from_closure(api, clip.core(), move |_| {
+            let mut out = OwnedMap::new(api);
+            out.set_error("closure error").unwrap();
+            out
+        });
+        let mut out = OwnedMap::new(api);
+        failing.call(&OwnedMap::new(api), &mut out);
+        assert_eq!(out.error().as_deref(), Some("closure error"));
+    }
+
+    #[test]
+    fn chunks_error() {
+        let mut env = vsscript::Environment::new().unwrap();
+        let clip = env
+            .eval_to_clip("test-vpy/green.vpy", vsscript::EvalFlags::Nothing)
+            .unwrap();
+
+        // A clip where frame 35 fails.
+        let api = API::get().unwrap();
+        let eval = {
+            let node = clip.node().clone();
+            Function::from_closure(api, clip.core(), move |in_| {
+                let mut out = OwnedMap::new(api);
+                if in_.get_int("n").unwrap() == 35 {
+                    out.set_error("frame 35 failed").unwrap();
+                } else {
+                    out.set_node("val", &node).unwrap();
+                }
+                out
+            })
+        };
+
+        let mut args = OwnedMap::new(api);
+        args.set_function("eval", &eval).unwrap();
+        let clip = clip.invoke("std", "FrameEval", args).unwrap();
+
+        let mut chunks = clip.node().chunks(30, 4);
+        assert_eq!(chunks.next().unwrap().unwrap().len(), 30);
+        match chunks.next() {
+            Some(Err(node::Error::GetFrame { frame, .. })) => assert_eq!(frame, 35),
+            _ => panic!("expected an error for frame 35"),
+        }
+        // The iteration stops after the first error.
+        assert!(chunks.next().is_none());
+    }
+
+    #[test]
+    fn render() {
+        let env =
+            vsscript::Environment::from_file("test-vpy/green.vpy", vsscript::EvalFlags::Nothing)
+                .unwrap();
+
+        #[cfg(feature = "gte-vsscript-api-31")]
+        let node = env.get_output(0).unwrap().0;
+        #[cfg(not(feature = "gte-vsscript-api-31"))]
+        let node = env.get_output(0).unwrap();
+
+        let mut output = Vec::new();
+        let stats = render::render(
+            &node,
+            render::RenderOptions {
+                start_frame: 98,
+                requests: 2,
+                ..Default::default()
+            },
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(stats.frames, 2);
+        assert_eq!(stats.mode, render::RenderMode::Default);
+
+        let y4m = render::render(
+            &node,
+            render::RenderOptions {
+                y4m: true,
+                ..Default::default()
+            },
+            io::sink(),
+        );
+        assert!(matches!(y4m, Err(render::Error::Y4mUnsupportedFormat(_))));
+
+        let range = render::render(
+            &node,
+            render::RenderOptions {
+                start_frame: 50,
+                end_frame: Some(100),
+                ..Default::default()
+            },
+            io::sink(),
+        );
+        assert!(matches!(range, Err(render::Error::InvalidRange { .. })));
+
+        let custom = |frames: Vec<usize>| {
+            render::render(
+                &node,
+                render::RenderOptions {
+                    requests: 2,
+                    order: render::FrameOrder::Custom(frames),
+                    ..Default::default()
+                },
+                io::sink(),
+            )
+        };
+        assert_eq!(custom(vec![3, 3, 1]).unwrap().frames, 3);
+        assert_eq!(custom(Vec::new()).unwrap().frames, 0);
+
+        struct Progress(Vec<String>);
+
+        impl render::ProgressReporter for Progress {
+            fn on_start(&mut self, total: usize) {
+                self.0.push(format!("start {}", total));
+            }
+
+            fn on_frame(&mut self, done: usize) {
+                self.0.push(format!("frame {}", done));
+            }
+
+            fn on_finish(&mut self) {
+                self.0.push("finish".to_owned());
+            }
+        }
+
+        let mut progress = Progress(Vec::new());
+        render::render(
+            &node,
+            render::RenderOptions {
+                start_frame: 97,
+                requests: 2,
+                progress: Some(&mut progress),
+                ..Default::default()
+            },
+            io::sink(),
+        )
+        .unwrap();
+        assert_eq!(
+            progress.0,
+            ["start 3", "frame 1", "frame 2", "frame 3", "finish"]
+        );
+
+        let mut renderer = render::Renderer::new(&node, Default::default(), io::sink()).unwrap();
+        assert_eq!(renderer.render().unwrap().frames, 100);
+        assert!(matches!(
+            renderer.render(),
+            Err(render::Error::AlreadyRendered)
+        ));
+        renderer.finish().unwrap();
+        assert!(matches!(
+            custom(vec![0, 100]),
+            Err(render::Error::InvalidFrame {
+                frame: 100,
+                num_frames: 100
+            })
+        ));
+        drop(node);
+
+        let plane_size = 1920 * 1080;
+        assert_eq!(output.len(), plane_size * 3 * 2);
+        // RGB planes are output in the G, B, R order.
+        assert!(output[..plane_size].iter().all(|&x| x == 255));
+        assert!(output[plane_size..plane_size * 3].iter().all(|&x| x == 0));
+    }
+
+    #[test]
+    fn render_outputs_to_files() {
+        let env = vsscript::Environment::from_file(
+            "test-vpy/pixel-formats.vpy",
+            vsscript::EvalFlags::Nothing,
+        )
+        .unwrap();
+
+        let dir = std::env::temp_dir();
+        let first = dir.join("vapoursynth-rs-render-output-0.raw");
+        let second = dir.join("vapoursynth-rs-render-output-1.raw");
+        let options = render::RenderOptions {
+            end_frame: Some(1),
+            ..Default::default()
+        };
+
+        let stats = render::render_outputs_to_files(
+            &env,
+            &[(0, first.clone()), (1, second.clone())],
+            &options,
+        )
+        .unwrap();
+        assert_eq!(stats.len(), 2);
+        assert!(stats.iter().all(|stats| stats.frames == 2));
+        assert_eq!(
+            std::fs::metadata(&first).unwrap().len(),
+            2 * 320 * 240 * 2 * 3 / 2
+        );
+        assert_eq!(
+            std::fs::metadata(&second).unwrap().len(),
+            2 * 320 * 240 * 4 * 3
+        );
+
+        let result = render::render_outputs_to_files(
+            &env,
+            &[(0, first.clone()), (42, second.clone())],
+            &options,
+        );
+        match result {
+            Err(render::Error::Outputs(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert_eq!(errors[0].0, 42);
+                assert!(matches!(errors[0].1, render::Error::GetOutput { .. }));
+            }
+            _ => panic!("expected an output error"),
+        }
+
+        std::fs::remove_file(first).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+
+    #[test]
+    fn render_job() {
+        let timecodes = std::env::temp_dir().join("vapoursynth-rs-render-job-timecodes.txt");
+
+        let mut output = Vec::new();
+        let stats = render::RenderJob::from_script("test-vpy/green.vpy")
+            .eval_flags(vsscript::EvalFlags::Nothing)
+            .output_index(0)
+            .range(97..100)
+            .requests(2)
+            .timecodes(&timecodes)
+            .run(&mut output)
+            .unwrap();
+        assert_eq!(stats.frames, 3);
+        assert_eq!(stats.requests, 2);
+        assert_eq!(output.len(), 3 * 1920 * 1080 * 3);
+
+        let timecodes_data = std::fs::read_to_string(&timecodes).unwrap();
+        assert_eq!(timecodes_data.lines().count(), 4);
+        std::fs::remove_file(timecodes).unwrap();
+
+        // The first invalid argument is reported.
+        #[allow(clippy::reversed_empty_ranges)]
+        let result = render::RenderJob::from_script("test-vpy/green.vpy")
+            .requests(0)
+            .range(10..5)
+            .run(Vec::new());
+        assert!(matches!(result, Err(render::Error::ZeroRequests)));
+
+        let result = render::RenderJob::from_script("test-vpy/green.vpy")
+            .range(5..5)
+            .run(Vec::new());
+        assert!(matches!(
+            result,
+            Err(render::Error::EmptyRange { start: 5, end: 5 })
+        ));
+
+        let result = render::RenderJob::from_script("test-vpy/green.vpy")
+            .range(0..101)
+            .run(Vec::new());
+        assert!(matches!(result, Err(render::Error::InvalidRange { .. })));
+
+        let result = render::RenderJob::from_script("test-vpy/green.vpy")
+            .output_index(42)
+            .run(Vec::new());
+        assert!(matches!(
+            result,
+            Err(render::Error::GetOutput { index: 42, .. })
+        ));
+
+        let result = render::RenderJob::from_script("test-vpy/nonexistent.vpy").run(Vec::new());
+        assert!(matches!(result, Err(render::Error::Script(_))));
+    }
+
+    #[test]
+    fn y4m_header_first_rendered_frame() {
+        let mut env = vsscript::Environment::new().unwrap();
+        let clip = env
+            .eval_to_clip("test-vpy/pixel-formats.vpy", vsscript::EvalFlags::Nothing)
+            .unwrap();
+
+        // A clip where frame 0 fails, so it can't be used for the header.
+        let api = API::get().unwrap();
+        let eval = {
+            let node = clip.node().clone();
+            Function::from_closure(api, clip.core(), move |in_| {
+                let mut out = OwnedMap::new(api);
+                if in_.get_int("n").unwrap() == 0 {
+                    out.set_error("frame 0 failed").unwrap();
+                } else {
+                    out.set_node("val", &node).unwrap();
+                }
+                out
+            })
+        };
+
+        let mut args = OwnedMap::new(api);
+        args.set_function("eval", &eval).unwrap();
+        let clip = clip.invoke("std", "FrameEval", args).unwrap();
+
+        let mut output = Vec::new();
+        let stats = render::render(
+            clip.node(),
+            render::RenderOptions {
+                start_frame: 1,
+                end_frame: Some(2),
+                y4m: true,
+                ..Default::default()
+            },
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(stats.frames, 2);
+        assert!(output.starts_with(b"YUV4MPEG2 C420p10 W320 H240 F60:1 Ip A0:0"));
+    }
+
+    #[test]
+    fn y4m_writer() {
+        let env = vsscript::Environment::from_file(
+            "test-vpy/pixel-formats.vpy",
+            vsscript::EvalFlags::Nothing,
+        )
+        .unwrap();
+
+        #[cfg(feature = "gte-vsscript-api-31")]
+        let node = env.get_output(0).unwrap().0;
+        #[cfg(not(feature = "gte-vsscript-api-31"))]
+        let node = env.get_output(0).unwrap();
+
+        let mut writer = render::Y4mWriter::from_node(Vec::new(), &node).unwrap();
+        let frame = node.get_frame(0).unwrap();
+        assert_eq!(frame.luma_pixel_count(), 320 * 240);
+        assert_eq!(frame.pixel_count(1), 160 * 120);
+        writer.write_frame(&frame).unwrap();
+        writer.write_frame(&frame).unwrap();
+        assert_eq!(writer.frames_written(), 2);
+
+        let output = writer.into_inner();
+        let header = b"YUV4MPEG2 C420p10 W320 H240 F60:1 Ip A0:0 XLENGTH=100\n";
+        assert!(output.starts_with(header));
+        let frame_size = 320 * 240 * 2 * 3 / 2;
+        assert_eq!(
+            output.len(),
+            header.len() + 2 * (b"FRAME\n".len() + frame_size)
+        );
+
+        #[cfg(feature = "gte-vsscript-api-31")]
+        let rgb = env.get_output(2).unwrap().0;
+        #[cfg(not(feature = "gte-vsscript-api-31"))]
+        let rgb = env.get_output(2).unwrap();
+
+        assert!(matches!(
+            render::Y4mWriter::from_node(io::sink(), &rgb),
+            Err(render::Error::Y4mUnsupportedFormat(None))
+        ));
+    }
+
+    #[test]
+    fn packed_frames() {
+        let env =
+            vsscript::Environment::from_file("test-vpy/green.vpy", vsscript::EvalFlags::Nothing)
+                .unwrap();
+
+        #[cfg(feature = "gte-vsscript-api-31")]
+        let node = env.get_output(0).unwrap().0;
+        #[cfg(not(feature = "gte-vsscript-api-31"))]
+        let node = env.get_output(0).unwrap();
+
+        let frames = node.frames(10..20, 3);
+        assert_eq!(frames.len(), 10);
+        for frame in frames {
+            green_frame_test(&frame.unwrap());
+        }
+
+        let chunk_lengths = node
+            .chunks(30, 4)
+            .map(|chunk| chunk.unwrap().len())
+            .collect::<Vec<_>>();
+        assert_eq!(chunk_lengths, [30, 30, 30, 10]);
+
+        let plane_size = 1920 * 1080;
+        let mut expected = vec![0; plane_size * 3];
+        // RGB planes are output in the G, B, R order.
+        expected[..plane_size].iter_mut().for_each(|x| *x = 255);
+
+        let mut count = 0;
+        for data in node.packed_frames(95..100, 2) {
+            assert!(data.unwrap() == expected);
+            count += 1;
+        }
+        assert_eq!(count, 5);
+
+        let results = node.packed_frames(99..101, 2).collect::<Vec<_>>();
+        assert!(results[0].is_ok());
+        match results[1] {
+            Err(node::Error::GetFrame { frame, .. }) => assert_eq!(frame, 100),
+            _ => panic!("expected an error for frame 100"),
+        }
+    }
+
     #[test]
     fn get_frame_async_error() {
         let env =
@@ -519,6 +1162,17 @@ mod need_api_and_vsscript {
         node.get_frame_async(100, move |frame, n, node| {
             assert!(frame.is_err());
             assert_eq!(n, 100);
+
+            let error = frame.unwrap_err();
+            assert_eq!(error.frame(), 100);
+            let message = error.to_string();
+            match node::Error::from(error) {
+                node::Error::GetFrame { frame, message: m } => {
+                    assert_eq!(frame, 100);
+                    assert_eq!(m, message);
+                }
+                _ => panic!("expected a GetFrame error"),
+            }
             assert_eq!(
                 node.info().framerate,
                 Property::Constant(Framerate {
@@ -531,6 +1185,9 @@ mod need_api_and_vsscript {
         });
 
         assert_eq!(rx.recv(), Ok(()));
+
+        let error = node.get_frame(100).unwrap_err();
+        assert_eq!(error.frame(), 100);
     }
 
     #[test]
@@ -676,12 +1333,33 @@ mod need_api_and_vsscript {
 ))]
 mod need_api {
     use std::ffi::CString;
+    use std::io;
     use std::sync::mpsc::{channel, Sender};
     use std::sync::Mutex;
 
     use super::*;
     use prelude::*;
 
+    #[test]
+    fn vsmap_macro() {
+        let map = crate::vsmap! {
+            "width" => 1920i64,
+            "height" => 1080,
+            "fps" => 23.976,
+            "name" => "clip",
+        }
+        .unwrap();
+
+        assert_eq!(map.key_count(), 4);
+        assert_eq!(map.get_int("width"), Ok(1920));
+        assert_eq!(map.get_int("height"), Ok(1080));
+        assert_eq!(map.get_float("fps"), Ok(23.976));
+        assert_eq!(map.get_data("name"), Ok(&b"clip"[..]));
+
+        let map = crate::vsmap!(API::get().unwrap(); "invalid key" => 1);
+        assert!(matches!(map, Err(map::Error::InvalidKey(_))));
+    }
+
     #[test]
     fn maps() {
         let mut map = OwnedMap::new(API::get().unwrap());
@@ -734,6 +1412,9 @@ mod need_api {
             assert_eq!(map.get_int_array("ia"), Ok(&[10, 20, 30][..]));
         }
 
+        assert_eq!(map.get_int_vec("i"), Ok(vec![42, 43]));
+        assert_eq!(map.get_int_vec("missing"), Err(map::Error::KeyNotFound));
+
         assert_eq!(map.set_float("f", 42f64), Ok(()));
         assert_eq!(map.get_float("f"), Ok(42f64));
         assert_eq!(map.append_float("f", 43f64), Ok(()));
@@ -768,6 +1449,8 @@ mod need_api {
             assert_eq!(map.get_float_array("fa"), Ok(&[10f64, 20f64, 30f64][..]));
         }
 
+        assert_eq!(map.get_float_vec("f"), Ok(vec![42f64, 43f64]));
+
         assert_eq!(map.set_data("d", &[1, 2, 3]), Ok(()));
         assert_eq!(map.get_data("d"), Ok(&[1, 2, 3][..]));
         assert_eq!(map.append_data("d", &[4, 5, 6]), Ok(()));
@@ -794,6 +1477,11 @@ mod need_api {
             assert_eq!(iter.next(), None);
         }
 
+        assert_eq!(
+            map.get_data_vec("d"),
+            Ok(vec![vec![1, 2, 3], vec![4, 5, 6]])
+        );
+
         // TODO: node, frame and function method tests when we can make them.
 
         assert_eq!(map.delete_key("test_frame"), Ok(()));
@@ -1028,12 +1716,479 @@ mod need_api {
         assert_eq!(yuv422p8.sub_sampling_w(), 1);
         assert_eq!(yuv422p8.sub_sampling_h(), 0);
 
+        assert_eq!(yuv420p8.frame_size_bytes(1920, 1080), 1920 * 1080 * 3 / 2);
+        assert_eq!(yuv422p8.frame_size_bytes(1920, 1080), 1920 * 1080 * 2);
+
+        assert_eq!(yuv420p8.validate_dimensions(1920, 1080), Ok(()));
+        assert_eq!(
+            yuv420p8.validate_dimensions(1921, 1080),
+            Err(format::Error::InvalidDimension {
+                dimension: "width",
+                value: 1921,
+                divisor: 2,
+            })
+        );
+        assert_eq!(yuv422p8.validate_dimensions(1920, 1081), Ok(()));
+
+        assert_eq!(yuv420p8.subsampling(), Ok(format::Subsampling::S420));
+        assert_eq!(yuv422p8.subsampling(), Ok(format::Subsampling::S422));
+        assert_eq!(format::Subsampling::S422.y4m_tag(), "422");
+        assert_eq!(format::Subsampling::S411.to_wh(), (2, 0));
+
+        assert!(yuv420p8.is_yuv());
+        assert!(!yuv420p8.is_rgb() && !yuv420p8.is_gray());
+        assert!(yuv420p8.is_compatible_with(yuv420p8));
+        assert!(!yuv420p8.is_compatible_with(yuv422p8));
+        assert_eq!(
+            yuv420p8.compatibility(yuv422p8),
+            Err(format::Error::SubsamplingMismatch {
+                left: (1, 1),
+                right: (1, 0),
+            })
+        );
+        let gray8 = core.get_format(PresetFormat::Gray8.into()).unwrap();
+        assert_eq!(
+            yuv420p8.compatibility(gray8),
+            Err(format::Error::ColorFamilyMismatch {
+                left: ColorFamily::YUV,
+                right: ColorFamily::Gray,
+            })
+        );
+        assert_eq!(
+            format::Subsampling::from_wh(2, 1),
+            Err(format::Error::UnsupportedSubsampling { w: 2, h: 1 })
+        );
+
         #[cfg(feature = "gte-vapoursynth-api-36")]
         {
             assert_eq!(core.set_max_cache_size(1337), 1337);
             assert_eq!(core.set_thread_count(3), 3);
             assert_eq!(core.info().max_framebuffer_size, 1337);
             assert_eq!(core.info().num_threads, 3);
+            assert_eq!(core.num_threads(), 3);
+            assert_eq!(core.set_thread_count(2), 2);
+            assert_eq!(core.num_threads(), 2);
         }
     }
+
+    #[test]
+    #[should_panic(expected = "width")]
+    fn new_uninitialized_invalid_dimensions() {
+        let api = API::get().unwrap();
+        let core = api.create_core(1);
+        let format = core.get_format(PresetFormat::YUV420P8.into()).unwrap();
+
+        let _ = unsafe {
+            FrameRefMut::new_uninitialized(
+                core,
+                None,
+                format,
+                video_info::Resolution {
+                    width: 1921,
+                    height: 1080,
+                },
+            )
+        };
+    }
+
+    #[test]
+    fn supports() {
+        let api = API::get().unwrap();
+        assert!(api.version() >= vapoursynth_sys::VAPOURSYNTH_API_VERSION);
+        assert!(api.supports(api::ApiFunction::SetThreadCount));
+
+        #[cfg(feature = "gte-vapoursynth-api-31")]
+        assert!(api.supports(api::ApiFunction::PropGetIntArray));
+
+        #[cfg(feature = "gte-vapoursynth-api-36")]
+        assert!(api.supports(api::ApiFunction::GetCoreInfo2));
+
+        // getCoreInfo2() is newer than the rest, so its support follows the runtime version.
+        let version_36 = (3 << 16) | 6;
+        assert_eq!(api::ApiFunction::GetCoreInfo2.min_api_version(), version_36);
+        assert_eq!(
+            api.supports(api::ApiFunction::GetCoreInfo2),
+            api.version() >= version_36
+        );
+    }
+
+    #[test]
+    fn new_video_frame_from_planes() {
+        let api = API::get().unwrap();
+        let core = api.create_core(1);
+        let format = core.get_format(PresetFormat::YUV420P8.into()).unwrap();
+
+        let luma = (0..16 * 8).map(|x| x as u8).collect::<Vec<_>>();
+        let chroma = vec![128u8; 8 * 4];
+
+        let frame = core
+            .new_video_frame_from_planes(format, 16, 8, &[&luma, &chroma, &chroma])
+            .unwrap();
+        assert_eq!(
+            frame.resolution(0),
+            video_info::Resolution {
+                width: 16,
+                height: 8
+            }
+        );
+        for row in 0..8 {
+            assert_eq!(frame.data_row(0, row), &luma[row * 16..(row + 1) * 16]);
+        }
+        for row in 0..4 {
+            assert_eq!(frame.data_row(1, row), &chroma[..8]);
+            assert_eq!(frame.data_row(2, row), &chroma[..8]);
+        }
+        assert_eq!(
+            frame.plane_f64(0).err(),
+            Some(frame::Error::InvalidComponentType)
+        );
+
+        assert_eq!(
+            core.new_video_frame_from_planes(format, 16, 7, &[&luma, &chroma, &chroma])
+                .err(),
+            Some(frame::Error::Format(format::Error::InvalidDimension {
+                dimension: "height",
+                value: 7,
+                divisor: 2,
+            }))
+        );
+        assert_eq!(
+            core.new_video_frame_from_planes(format, 16, 8, &[&luma])
+                .err(),
+            Some(frame::Error::WrongPlaneCount {
+                expected: 3,
+                got: 1,
+            })
+        );
+        assert_eq!(
+            core.new_video_frame_from_planes(format, 16, 8, &[&luma, &chroma, &chroma[1..]])
+                .err(),
+            Some(frame::Error::WrongPlaneSize {
+                plane: 2,
+                expected: 32,
+                got: 31,
+            })
+        );
+    }
+
+    #[test]
+    fn frame_difference() {
+        let api = API::get().unwrap();
+        let core = api.create_core(1);
+        let format = core.get_format(PresetFormat::Gray16.into()).unwrap();
+
+        let a = [0u16, 10, 65535, 300];
+        let b = [5u16, 3, 0, 300];
+        let to_bytes = |x: &[u16]| x.iter().flat_map(|v| v.to_ne_bytes()).collect::<Vec<_>>();
+
+        let a = core
+            .new_video_frame_from_planes(format, 2, 2, &[&to_bytes(&a)])
+            .unwrap();
+        let b = core
+            .new_video_frame_from_planes(format, 2, 2, &[&to_bytes(&b)])
+            .unwrap();
+
+        let diff = a.difference(&b, core).unwrap();
+        assert_eq!(diff.plane_row::<u16>(0, 0), &[5, 7]);
+        assert_eq!(diff.plane_row::<u16>(0, 1), &[65535, 0]);
+
+        let gray8 = core.get_format(PresetFormat::Gray8.into()).unwrap();
+        let c = core
+            .new_video_frame_from_planes(gray8, 2, 2, &[&[0; 4]])
+            .unwrap();
+        assert_eq!(
+            a.difference(&c, core).err(),
+            Some(frame::Error::FormatMismatch)
+        );
+
+        assert_eq!(a.approx_equal(&a, 0), Ok(true));
+        assert_eq!(a.approx_equal(&b, 65534), Ok(false));
+        assert_eq!(a.approx_equal(&b, 65535), Ok(true));
+        assert_eq!(a.approx_equal(&c, 0), Err(frame::Error::FormatMismatch));
+    }
+
+    #[test]
+    fn frame_data_ptr() {
+        let api = API::get().unwrap();
+        let core = api.create_core(1);
+        let format = core.get_format(PresetFormat::Gray8.into()).unwrap();
+
+        let mut frame = core
+            .new_video_frame_from_planes(format, 3, 2, &[&[1, 2, 3, 4, 5, 6]])
+            .unwrap();
+
+        // Write the second row through the raw plane pointer, using the stride.
+        let stride = frame.stride(0);
+        let ptr = frame.data_ptr_mut(0);
+        unsafe {
+            ptr.add(stride)
+                .copy_from_nonoverlapping([7, 8, 9].as_ptr(), 3);
+            assert_eq!(*frame.data_ptr(0).add(stride + 2), 9);
+        }
+        assert_eq!(frame.data_row(0, 1), &[7, 8, 9]);
+    }
+
+    #[test]
+    fn frame_rows() {
+        let api = API::get().unwrap();
+        let core = api.create_core(1);
+        let format = core.get_format(PresetFormat::Gray8.into()).unwrap();
+
+        let mut frame = core
+            .new_video_frame_from_planes(format, 3, 2, &[&[1, 2, 3, 4, 5, 6]])
+            .unwrap();
+
+        let rows = frame.rows(0).collect::<Vec<_>>();
+        assert_eq!(rows, [&[1, 2, 3][..], &[4, 5, 6][..]]);
+
+        for (i, row) in frame.rows_mut(0).enumerate() {
+            row.iter_mut().for_each(|x| *x += i as u8 * 10);
+        }
+        assert_eq!(frame.plane_packed(0), [1, 2, 3, 14, 15, 16]);
+
+        assert_eq!(frame.plane_padding(0), frame.stride(0) - 3);
+        assert_eq!(frame.pixel_count(0), 6);
+        assert_eq!(frame.luma_pixel_count(), 6);
+        assert_eq!(
+            frame.plane_contiguous(0).is_some(),
+            frame.plane_padding(0) == 0
+        );
+    }
+
+    #[test]
+    fn frame_props_deletion() {
+        let api = API::get().unwrap();
+        let core = api.create_core(1);
+        let format = core.get_format(PresetFormat::Gray8.into()).unwrap();
+
+        let mut frame = core
+            .new_video_frame_from_planes(format, 2, 2, &[&[0; 4]])
+            .unwrap();
+        frame.props_mut().set_int("_SceneChangeNext", 1).unwrap();
+        frame.props_mut().set_int("_SceneChangePrev", 0).unwrap();
+        frame.props_mut().set_float("custom", 4.2).unwrap();
+
+        assert!(frame.has_prop("_SceneChangeNext"));
+        assert_eq!(frame.delete_prop("_SceneChangeNext"), Ok(()));
+        assert!(!frame.has_prop("_SceneChangeNext"));
+        assert!(!frame.props().contains_key("_SceneChangeNext"));
+        assert!(!frame.props().contains_key("invalid key"));
+        assert_eq!(
+            frame.props().get_int("_SceneChangeNext"),
+            Err(map::Error::KeyNotFound)
+        );
+        assert_eq!(frame.props().get_int("_SceneChangePrev"), Ok(0));
+        assert_eq!(
+            frame.delete_prop("_SceneChangeNext"),
+            Err(map::Error::KeyNotFound)
+        );
+
+        assert_eq!(frame.absolute_time(), None);
+        frame.props_mut().set_float("_AbsoluteTime", 1.5).unwrap();
+        assert_eq!(frame.absolute_time(), Some(1.5));
+
+        assert_eq!(frame.combed(), None);
+        frame.props_mut().set_int("_Combed", 1).unwrap();
+        assert_eq!(frame.combed(), Some(true));
+        assert_eq!(frame.field(), None);
+        frame.props_mut().set_int("_Field", 0).unwrap();
+        assert_eq!(frame.field(), Some(0));
+
+        frame.clear_props();
+        assert_eq!(frame.props().key_count(), 0);
+
+        assert!(frame.is_writable());
+        let frame = FrameRef::from(frame);
+        assert!(!frame.is_writable());
+        assert!(FrameRefMut::copy_of(core, &frame).is_writable());
+    }
+
+    #[test]
+    fn frames_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Frame>();
+        assert_send_sync::<FrameRef>();
+        assert_send_sync::<FrameRefMut>();
+        assert_send_sync::<Node>();
+
+        let api = API::get().unwrap();
+        let core = api.create_core(1);
+        let format = core.get_format(PresetFormat::Gray8.into()).unwrap();
+
+        let frame = core
+            .new_video_frame_from_planes(format, 2, 1, &[&[1, 2]])
+            .unwrap();
+
+        // Move the mutable frame to another thread and get it back as a shared one.
+        let frame = std::thread::scope(|scope| {
+            scope
+                .spawn(move || {
+                    let mut frame = frame;
+                    frame.props_mut().set_int("thread", 1).unwrap();
+                    FrameRef::from(frame)
+                })
+                .join()
+                .unwrap()
+        });
+
+        // Read the frame from several threads at once.
+        std::thread::scope(|scope| {
+            scope.spawn(|| assert_eq!(frame.data_row(0, 0), &[1, 2]));
+            scope.spawn(|| assert_eq!(frame.props().get_int("thread"), Ok(1)));
+        });
+    }
+
+    #[test]
+    fn frame_data_byte_order() {
+        let api = API::get().unwrap();
+        let core = api.create_core(1);
+        let format = core.get_format(PresetFormat::Gray16.into()).unwrap();
+
+        let samples = [0x0102u16, 0x0304];
+        let data = samples
+            .iter()
+            .flat_map(|x| x.to_ne_bytes())
+            .collect::<Vec<_>>();
+        let frame = core
+            .new_video_frame_from_planes(format, 2, 1, &[&data])
+            .unwrap();
+
+        let mut native = Vec::new();
+        render::write_frame_data(&mut native, &frame, false).unwrap();
+        assert_eq!(native, data);
+
+        let mut swapped = Vec::new();
+        render::write_frame_data(&mut swapped, &frame, true).unwrap();
+        let expected = samples
+            .iter()
+            .flat_map(|x| x.swap_bytes().to_ne_bytes())
+            .collect::<Vec<_>>();
+        assert_eq!(swapped, expected);
+
+        // Y4M output is always little-endian.
+        let y4m = if cfg!(target_endian = "big") {
+            swapped
+        } else {
+            native
+        };
+        let little_endian = samples
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect::<Vec<_>>();
+        assert_eq!(y4m, little_endian);
+    }
+
+    #[test]
+    fn core_creation_flags() {
+        use crate::core::CoreCreationFlags;
+
+        let api = API::get().unwrap();
+
+        let core = api.create_core_with_flags(1, CoreCreationFlags::default());
+        assert_eq!(core.num_threads(), 1);
+
+        // API 3 can't honor the flags, so they're ignored.
+        let core = api.create_core_with_flags(1, CoreCreationFlags::DISABLE_AUTO_LOADING);
+        assert_eq!(core.num_threads(), 1);
+    }
+
+    #[test]
+    fn frame_into_props() {
+        let api = API::get().unwrap();
+        let core = api.create_core(1);
+        let format = core.get_format(PresetFormat::Gray8.into()).unwrap();
+
+        let other = core
+            .new_video_frame_from_planes(format, 2, 2, &[&[7; 4]])
+            .unwrap();
+        let mut frame = core
+            .new_video_frame_from_planes(format, 2, 2, &[&[0; 4]])
+            .unwrap();
+        {
+            let mut props = frame.props_mut();
+            props.set_int("_DurationNum", 1001).unwrap();
+            props.append_int("_DurationNum", 1002).unwrap();
+            props.set_float("custom", 4.2).unwrap();
+            props.set_data("name", b"frame").unwrap();
+            props.set_frame("other", &other).unwrap();
+            props.touch("empty", ValueType::Node).unwrap();
+        }
+        drop(other);
+
+        let props = FrameRef::from(frame).into_props();
+        assert_eq!(props.key_count(), 5);
+        assert_eq!(props.get_int_vec("_DurationNum"), Ok(vec![1001, 1002]));
+        assert_eq!(props.get_float("custom"), Ok(4.2));
+        assert_eq!(props.get_data("name"), Ok(&b"frame"[..]));
+        assert_eq!(props.get_frame("other").unwrap().data_row(0, 1), &[7, 7]);
+        assert_eq!(props.value_type("empty"), Ok(ValueType::Node));
+        assert_eq!(props.value_count("empty"), Ok(0));
+    }
+
+    // A writer recording the written bytes and flushes, or failing with the given message.
+    #[derive(Default)]
+    struct TestWriter {
+        data: Vec<u8>,
+        flushes: usize,
+        error: Option<&'static str>,
+    }
+
+    impl io::Write for TestWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            match self.error {
+                Some(error) => Err(io::Error::other(error)),
+                None => {
+                    self.data.extend_from_slice(buf);
+                    Ok(buf.len())
+                }
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes += 1;
+            match self.error {
+                Some(error) => Err(io::Error::other(error)),
+                None => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn tee() {
+        use std::io::Write;
+
+        let mut tee = render::Tee::new(vec![TestWriter::default(), TestWriter::default()]);
+        tee.write_all(b"frame data").unwrap();
+        tee.flush().unwrap();
+        for writer in tee.get_ref() {
+            assert_eq!(writer.data, b"frame data");
+            assert_eq!(writer.flushes, 1);
+        }
+
+        // A single error is passed through.
+        let failing = TestWriter {
+            error: Some("first"),
+            ..Default::default()
+        };
+        let mut tee = render::Tee::new(vec![failing, TestWriter::default()]);
+        assert_eq!(tee.write(b"data").unwrap_err().to_string(), "first");
+        assert_eq!(tee.get_ref()[1].data, b"data");
+
+        // Errors of several writers are combined, and all writers are flushed.
+        let failing = |error| TestWriter {
+            error: Some(error),
+            ..Default::default()
+        };
+        let mut tee = render::Tee::new(vec![
+            failing("first"),
+            TestWriter::default(),
+            failing("second"),
+        ]);
+        assert_eq!(tee.write(b"data").unwrap_err().to_string(), "first; second");
+        assert_eq!(tee.flush().unwrap_err().to_string(), "first; second");
+
+        let writers = tee.into_inner();
+        assert_eq!(writers[1].data, b"data");
+        assert!(writers.iter().all(|writer| writer.flushes == 1));
+    }
 }
@@ -1,6 +1,6 @@
 //! Video clip formats.
 
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display};
 use std::ops::Deref;
 use std::ptr;
 use vapoursynth_sys as ffi;
@@ -129,6 +129,23 @@ impl<'core> VideoInfo<'core> {
         }
     }
 
+    /// Returns the length of the clip, or `None` if it's unknown.
+    ///
+    /// Unlike `num_frames`, this has the same type regardless of the API version.
+    #[inline]
+    pub fn frame_count(&self) -> Option<usize> {
+        #[cfg(feature = "gte-vapoursynth-api-32")]
+        let rv = Some(self.num_frames);
+
+        #[cfg(not(feature = "gte-vapoursynth-api-32"))]
+        let rv = match self.num_frames {
+            Property::Variable => None,
+            Property::Constant(x) => Some(x),
+        };
+
+        rv
+    }
+
     /// Converts the Rust struct into a C struct.
     pub(crate) fn ffi_type(self) -> ffi::VSVideoInfo {
         let format = match self.format {
@@ -172,6 +189,50 @@ impl<'core> VideoInfo<'core> {
     }
 }
 
+impl Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+impl Display for Framerate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+impl<'core> Display for VideoInfo<'core> {
+    /// Formats the clip information in one line, for example
+    /// `YUV420P8, 1920x1080, 24000/1001 fps, 100 frames`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.format {
+            Property::Variable => write!(f, "variable format")?,
+            Property::Constant(format) => write!(f, "{}", format.name())?,
+        }
+
+        match self.resolution {
+            Property::Variable => write!(f, ", variable resolution")?,
+            Property::Constant(resolution) => write!(f, ", {}", resolution)?,
+        }
+
+        match self.framerate {
+            Property::Variable => write!(f, ", variable fps")?,
+            Property::Constant(framerate) => write!(f, ", {} fps", framerate)?,
+        }
+
+        #[cfg(feature = "gte-vapoursynth-api-32")]
+        write!(f, ", {} frames", self.num_frames)?;
+
+        #[cfg(not(feature = "gte-vapoursynth-api-32"))]
+        match self.num_frames {
+            Property::Variable => write!(f, ", unknown length")?,
+            Property::Constant(num_frames) => write!(f, ", {} frames", num_frames)?,
+        }
+
+        Ok(())
+    }
+}
+
 impl<T> From<T> for Property<T>
 where
     T: Debug + Clone + Copy + Eq + PartialEq,
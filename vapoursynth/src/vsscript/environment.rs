@@ -8,32 +8,52 @@ use std::ptr::NonNull;
 use vapoursynth_sys as ffi;
 
 use crate::api::API;
+use crate::clip::Clip;
 use crate::core::CoreRef;
-use crate::map::Map;
+use crate::map::{Map, OwnedMap};
 use crate::node::Node;
+use crate::video_info::VideoInfo;
 use crate::vsscript::errors::Result;
 use crate::vsscript::*;
 
 use crate::vsscript::VSScriptError;
 
-/// VSScript file evaluation flags.
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
-pub enum EvalFlags {
-    Nothing,
-    /// The working directory will be changed to the script's directory for the evaluation.
-    SetWorkingDir,
+bitflags! {
+    /// VSScript file evaluation flags.
+    #[derive(Default)]
+    pub struct EvalFlags: i32 {
+        /// The working directory will be changed to the script's directory for the evaluation.
+        const SET_WORKING_DIR = ffi::VSEvalFlags::efSetWorkingDir as i32;
+    }
 }
 
+#[allow(non_upper_case_globals)]
 impl EvalFlags {
+    /// No flags, the same as `EvalFlags::empty()`.
+    pub const Nothing: Self = Self::empty();
+
+    /// The same as `EvalFlags::SET_WORKING_DIR`.
+    pub const SetWorkingDir: Self = Self::SET_WORKING_DIR;
+
     #[inline]
     fn ffi_type(self) -> ::std::os::raw::c_int {
-        match self {
-            EvalFlags::Nothing => 0,
-            EvalFlags::SetWorkingDir => ffi::VSEvalFlags::efSetWorkingDir as _,
-        }
+        self.bits()
     }
 }
 
+/// Contains information about an output set in the script environment.
+#[derive(Debug, Clone, Copy)]
+pub struct OutputInfo<'core> {
+    /// The output index.
+    pub index: i32,
+
+    /// The video info of the output node.
+    pub info: VideoInfo<'core>,
+
+    /// Whether the output has an alpha node.
+    pub has_alpha: bool,
+}
+
 /// Contains two possible variants of arguments to `Environment::evaluate_script()`.
 #[derive(Clone, Copy)]
 enum EvaluateScriptArgs<'a> {
@@ -132,6 +152,31 @@ impl Environment {
         }
     }
 
+    /// Evaluates a short script used to implement functionality missing from the VSScript API.
+    ///
+    /// Unlike `evaluate_script()`, this takes `&self`, because it's used together with nodes (and
+    /// the core they're created with) that usually borrow the environment. Another thread may
+    /// change the error message before it's retrieved, but these scripts are not expected to fail
+    /// in the first place.
+    fn evaluate_helper_script(&self, script: &str) -> Result<()> {
+        let script = CString::new(script)?;
+
+        let rv = unsafe {
+            call_vsscript!(ffi::vsscript_evaluateScript(
+                &mut self.handle.as_ptr(),
+                script.as_ptr(),
+                ptr::null(),
+                EvalFlags::Nothing.ffi_type(),
+            ))
+        };
+
+        if rv != 0 {
+            Err(VSScriptError::new(unsafe { self.error() }).into())
+        } else {
+            Ok(())
+        }
+    }
+
     /// Creates a script environment and evaluates a script contained in a string.
     #[inline]
     pub fn from_script(script: &str) -> Result<Self> {
@@ -212,6 +257,116 @@ impl Environment {
         Ok((node, alpha_node))
     }
 
+    /// Marks a node for output with the given index, with an optional alpha node.
+    ///
+    /// VSScript API 3 has no function for setting outputs, so this sets temporary variables in
+    /// the script environment and evaluates a short script calling `set_output()` on them. The
+    /// node then becomes available to `get_output()` and to any tools consuming the environment's
+    /// outputs like those of a regular script.
+    ///
+    /// An output set with this function replaces any output previously set on the same index,
+    /// including by a script, and is in turn replaced if a script evaluated afterwards sets an
+    /// output on that index.
+    ///
+    /// Passing an alpha node requires a VapourSynth version with alpha output support (R43 and
+    /// above).
+    #[cfg(any(feature = "vapoursynth-functions", feature = "gte-vsscript-api-32"))]
+    pub fn set_output(&self, index: i32, node: &Node, alpha_node: Option<&Node>) -> Result<()> {
+        const NODE_VARIABLE: &str = "__vapoursynth_rs_output_node";
+        const ALPHA_VARIABLE: &str = "__vapoursynth_rs_output_alpha";
+
+        let api = API::get().ok_or(Error::NoAPI)?;
+
+        let mut variables = OwnedMap::new(api);
+        variables.set_node(NODE_VARIABLE, node).unwrap();
+        if let Some(alpha_node) = alpha_node {
+            variables.set_node(ALPHA_VARIABLE, alpha_node).unwrap();
+        }
+        self.set_variables(&variables)?;
+
+        let script = if alpha_node.is_some() {
+            format!(
+                "{}.set_output({}, alpha={})",
+                NODE_VARIABLE, index, ALPHA_VARIABLE
+            )
+        } else {
+            format!("{}.set_output({})", NODE_VARIABLE, index)
+        };
+        let rv = self.evaluate_helper_script(&script);
+
+        self.clear_variable(NODE_VARIABLE)?;
+        if alpha_node.is_some() {
+            self.clear_variable(ALPHA_VARIABLE)?;
+        }
+
+        rv
+    }
+
+    /// Returns the indices of all outputs set in the script environment, in ascending order.
+    ///
+    /// VSScript API 3 has no function for this, so it's implemented by evaluating a short script
+    /// which stores the indices and their count in temporary variables.
+    #[cfg(any(feature = "vapoursynth-functions", feature = "gte-vsscript-api-32"))]
+    pub fn output_indices(&self) -> Result<Vec<i32>> {
+        const INDICES_VARIABLE: &str = "__vapoursynth_rs_output_indices";
+        const COUNT_VARIABLE: &str = "__vapoursynth_rs_output_count";
+
+        let api = API::get().ok_or(Error::NoAPI)?;
+
+        self.evaluate_helper_script(&format!(
+            "import vapoursynth\n{0} = sorted(vapoursynth.get_outputs().keys())\n{1} = len({0})",
+            INDICES_VARIABLE, COUNT_VARIABLE
+        ))?;
+
+        let mut map = OwnedMap::new(api);
+        let indices = self.get_variable(COUNT_VARIABLE, &mut map).and_then(|()| {
+            // An empty list can't be converted into a map value, so it's only retrieved if it
+            // has elements.
+            let count = map
+                .get_int(COUNT_VARIABLE)
+                .map_err(|_| Error::NoSuchVariable)?;
+            if count == 0 {
+                return Ok(Vec::new());
+            }
+
+            self.get_variable(INDICES_VARIABLE, &mut map)?;
+            map.get_int_iter(INDICES_VARIABLE)
+                .map(|iter| iter.map(|x| x as i32).collect())
+                .map_err(|_| Error::NoSuchVariable)
+        });
+
+        self.clear_variable(INDICES_VARIABLE)?;
+        self.clear_variable(COUNT_VARIABLE)?;
+
+        indices
+    }
+
+    /// Returns the video info of every output set in the script environment.
+    ///
+    /// The outputs are ordered by their index.
+    ///
+    /// The video info is only available from the nodes, so this retrieves the node of every output
+    /// (the alpha node as well, with VSScript API 3.1 or greater). Retrieving a node doesn't request
+    /// any frames.
+    #[cfg(any(feature = "vapoursynth-functions", feature = "gte-vsscript-api-32"))]
+    pub fn outputs_info(&self) -> Result<Vec<OutputInfo<'_>>> {
+        self.output_indices()?
+            .into_iter()
+            .map(|index| {
+                #[cfg(feature = "gte-vsscript-api-31")]
+                let (node, alpha_node) = self.get_output(index)?;
+                #[cfg(not(feature = "gte-vsscript-api-31"))]
+                let (node, alpha_node) = (self.get_output(index)?, None::<Node>);
+
+                Ok(OutputInfo {
+                    index,
+                    info: node.info(),
+                    has_alpha: alpha_node.is_some(),
+                })
+            })
+            .collect()
+    }
+
     /// Cancels a node set for output. The node will no longer be available to `get_output()`.
     #[inline]
     pub fn clear_output(&self, index: i32) -> Result<()> {
@@ -223,6 +378,31 @@ impl Environment {
         }
     }
 
+    /// Evaluates a script contained in a file and returns its output with index 0 as a `Clip`.
+    ///
+    /// The environment stays mutably borrowed for as long as the clip is alive. To keep using the
+    /// environment, call `eval_file()` and then `output_clip()` instead.
+    #[cfg(any(feature = "vapoursynth-functions", feature = "gte-vsscript-api-32"))]
+    pub fn eval_to_clip<P: AsRef<Path>>(&mut self, path: P, flags: EvalFlags) -> Result<Clip<'_>> {
+        self.eval_file(path, flags)?;
+        self.output_clip(0)
+    }
+
+    /// Returns the output with the given index as a `Clip`.
+    ///
+    /// The alpha node of the output, if any, is not part of the clip.
+    #[cfg(any(feature = "vapoursynth-functions", feature = "gte-vsscript-api-32"))]
+    pub fn output_clip(&self, index: i32) -> Result<Clip<'_>> {
+        let core = self.get_core()?;
+
+        #[cfg(feature = "gte-vsscript-api-31")]
+        let node = self.get_output(index)?.0;
+        #[cfg(not(feature = "gte-vsscript-api-31"))]
+        let node = self.get_output(index)?;
+
+        Ok(Clip::new(core, node))
+    }
+
     /// Retrieves the VapourSynth core that was created in the script environment. If a VapourSynth
     /// core has not been created yet, it will be created now, with the default options.
     #[cfg(any(feature = "vapoursynth-functions", feature = "gte-vsscript-api-32"))]
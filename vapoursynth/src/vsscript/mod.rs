@@ -66,4 +66,4 @@ mod errors;
 pub use self::errors::{Error, VSScriptError};
 
 mod environment;
-pub use self::environment::{Environment, EvalFlags};
+pub use self::environment::{Environment, EvalFlags, OutputInfo};